@@ -0,0 +1,63 @@
+use crate::error::ConfigError;
+
+/// Deletions after which the deleted prefix of the list is purged.
+pub(crate) const DEFAULT_PURGE_THRESHOLD: u32 = 64;
+
+/// What [`insert`](crate::MDList::insert) does with a key that is already in
+/// the queue.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Duplicates {
+    /// Keep every entry; entries with equal keys are popped in the order they
+    /// were inserted.
+    #[default]
+    Keep,
+    /// Fail the insertion with [`InsertError::DuplicateKey`](crate::InsertError::DuplicateKey).
+    Reject,
+    /// Replace the stored value and drop the old one.
+    Replace,
+}
+
+/// Construction parameters for an [`MDList`](crate::MDList).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Exclusive upper bound of the keys the queue accepts. `None` accepts
+    /// every value of the key type.
+    pub range: Option<u128>,
+    /// Number of deletions after which the deleted prefix is unlinked from
+    /// the list in one batch.
+    pub purge_threshold: u32,
+    /// How inserting a key that is already present is handled.
+    pub duplicates: Duplicates,
+}
+
+impl Config {
+    /// A configuration for keys below `range`, with the default purge
+    /// threshold and every duplicate kept.
+    pub fn new(range: u128) -> Self {
+        Config {
+            range: Some(range),
+            purge_threshold: DEFAULT_PURGE_THRESHOLD,
+            duplicates: Duplicates::Keep,
+        }
+    }
+
+    /// Checks the settings that do not depend on the list's key type and
+    /// shape; [`MDList::with_config`](crate::MDList::with_config) checks
+    /// the range against those.
+    pub(crate) fn validate(&self) -> Result<(), ConfigError> {
+        if self.purge_threshold == 0 {
+            return Err(ConfigError::InvalidPurgeThreshold);
+        }
+        Ok(())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            range: None,
+            purge_threshold: DEFAULT_PURGE_THRESHOLD,
+            duplicates: Duplicates::Keep,
+        }
+    }
+}
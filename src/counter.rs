@@ -0,0 +1,36 @@
+use std::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
+
+const STRIPES: usize = 16;
+
+static NEXT_STRIPE: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static STRIPE: usize = NEXT_STRIPE.fetch_add(1, Ordering::Relaxed) % STRIPES;
+}
+
+#[repr(align(64))]
+struct Stripe(AtomicIsize);
+
+/// Counter split over several cache lines so that threads updating it
+/// concurrently rarely touch the same one. Reading it sums every stripe and
+/// is only a snapshot while updates are in flight.
+pub(crate) struct StripedCounter {
+    stripes: [Stripe; STRIPES],
+}
+
+impl StripedCounter {
+    pub(crate) fn new() -> Self {
+        StripedCounter {
+            stripes: std::array::from_fn(|_| Stripe(AtomicIsize::new(0))),
+        }
+    }
+
+    pub(crate) fn add(&self, delta: isize) {
+        let stripe = STRIPE.with(|&stripe| stripe);
+        self.stripes[stripe].0.fetch_add(delta, Ordering::Relaxed);
+    }
+
+    pub(crate) fn sum(&self) -> isize {
+        self.stripes.iter().map(|stripe| stripe.0.load(Ordering::Relaxed)).sum()
+    }
+}
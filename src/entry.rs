@@ -0,0 +1,23 @@
+/// Names one entry of an [`MDList`](crate::MDList), as returned by
+/// [`insert_entry`](crate::MDList::insert_entry) and
+/// [`change_priority`](crate::MDList::change_priority).
+///
+/// The handle does not keep the entry alive; once the entry is popped,
+/// removed, moved or replaced, using the handle fails, as does using it with
+/// another list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntryHandle<K> {
+    /// Id of the list that handed the handle out.
+    pub(crate) list: usize,
+    pub(crate) key: K,
+    /// Insertion stamp of the entry, which tells it apart from other entries
+    /// under the same key.
+    pub(crate) seq: u32,
+}
+
+impl<K: Copy> EntryHandle<K> {
+    /// The key the entry is queued under.
+    pub fn key(&self) -> K {
+        self.key
+    }
+}
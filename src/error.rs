@@ -0,0 +1,130 @@
+use std::fmt;
+
+/// Returned when an [`MDList`](crate::MDList) is built from an invalid
+/// [`Config`](crate::Config) or with unusable dimension or basis parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The requested dimension is zero or exceeds what the list supports.
+    InvalidDimension {
+        /// The dimension asked for.
+        dimension: usize,
+        /// The largest dimension supported.
+        max: usize,
+    },
+    /// The coordinate basis is smaller than 2.
+    InvalidBasis {
+        /// The basis asked for.
+        basis: u32,
+    },
+    /// The key range does not fit into `dimension` coordinates, even in
+    /// the widest basis `u32` coordinates allow, `2^32`.
+    RangeTooLarge {
+        /// The range asked for.
+        range: u128,
+        /// The most keys the coordinates can hold.
+        capacity: u128,
+    },
+    /// No range was given and the key type is wider than the coordinates
+    /// can hold in any basis.
+    KeyTooWide {
+        /// Width of the key type in bits.
+        bits: u32,
+        /// The most keys the coordinates can hold.
+        capacity: u128,
+    },
+    /// The purge threshold is zero.
+    InvalidPurgeThreshold,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::InvalidDimension { dimension, max } => {
+                write!(f, "dimension {} is outside 1..={}", dimension, max)
+            }
+            ConfigError::InvalidBasis { basis } => write!(f, "basis {} is smaller than 2", basis),
+            ConfigError::RangeTooLarge { range, capacity } => {
+                write!(f, "range {} exceeds the {} keys the coordinates can hold", range, capacity)
+            }
+            ConfigError::KeyTooWide { bits, capacity } => {
+                write!(f, "{}-bit keys exceed the {} keys the coordinates can hold", bits, capacity)
+            }
+            ConfigError::InvalidPurgeThreshold => write!(f, "purge threshold must be at least 1"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Returned when a key cannot be inserted into an [`MDList`](crate::MDList).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InsertError {
+    /// The key is not below the range the list was configured with.
+    KeyOutOfRange {
+        /// The key rejected, as encoded.
+        key: u128,
+        /// The range the list was configured with.
+        range: u128,
+    },
+    /// The key is already in a list that rejects duplicates.
+    DuplicateKey {
+        /// The key rejected, as encoded.
+        key: u128,
+    },
+    /// The entry has already been popped, removed or moved.
+    EntryNotFound,
+}
+
+impl fmt::Display for InsertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InsertError::KeyOutOfRange { key, range } => {
+                write!(f, "key {} is outside 0..{}", key, range)
+            }
+            InsertError::DuplicateKey { key } => write!(f, "key {} is already present", key),
+            InsertError::EntryNotFound => write!(f, "entry is no longer in the list"),
+        }
+    }
+}
+
+impl std::error::Error for InsertError {}
+
+/// Returned when an [`MDList`](crate::MDList) cannot be built from a list of
+/// entries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    /// The configuration was rejected before any entry was inserted.
+    Config(ConfigError),
+    /// An entry could not be inserted; the list built so far is dropped.
+    Insert(InsertError),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::Config(e) => e.fmt(f),
+            BuildError::Insert(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BuildError::Config(e) => Some(e),
+            BuildError::Insert(e) => Some(e),
+        }
+    }
+}
+
+impl From<ConfigError> for BuildError {
+    fn from(e: ConfigError) -> Self {
+        BuildError::Config(e)
+    }
+}
+
+impl From<InsertError> for BuildError {
+    fn from(e: InsertError) -> Self {
+        BuildError::Insert(e)
+    }
+}
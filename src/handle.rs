@@ -0,0 +1,51 @@
+use crossbeam::epoch::{self, Owned};
+
+use crate::error::InsertError;
+use crate::key::PriorityKey;
+use crate::mdlist::{MDList, Stack, BASIS, DIMENSION};
+
+/// Per-thread access to an [`MDList`], created by [`MDList::handle`].
+///
+/// The handle holds on to a spare deletion stack, so repeated `pop` calls
+/// avoid most stack allocations. Each operation pins the thread only while
+/// it runs, so an idle handle does not hold back the reclamation of memory
+/// other threads retire. It borrows the queue it was created from.
+pub struct Handle<'a, V, K: PriorityKey = u32, const D: usize = DIMENSION, const B: u32 = BASIS> {
+    list: &'a MDList<V, K, D, B>,
+    spare: Option<Owned<Stack<V, K::Encoded, D, B>>>,
+}
+
+impl<'a, V, K: PriorityKey, const D: usize, const B: u32> Handle<'a, V, K, D, B> {
+    pub(crate) fn new(list: &'a MDList<V, K, D, B>) -> Self {
+        Handle {
+            list,
+            spare: None,
+        }
+    }
+
+    /// Inserts `val` under `key`. Fails like [`MDList::insert`].
+    pub fn push(&mut self, key: K, val: V) -> Result<(), InsertError> {
+        let key = key.encode();
+        self.list.check_key(key)?;
+        self.list.insert_with(key, val, &epoch::pin())
+    }
+
+    /// Removes the entry with the smallest key and returns it.
+    pub fn pop(&mut self) -> Option<(K, V)> {
+        let min = self.list.delete_min(&mut self.spare, &epoch::pin());
+        min.map(|(key, val)| (K::decode(key), val))
+    }
+
+    /// Removes up to `k` entries with the smallest keys and appends them to
+    /// `out`. See [`MDList::pop_min_n`].
+    pub fn pop_n(&mut self, k: usize, out: &mut Vec<(K, V)>) -> usize {
+        self.list.delete_min_n(k, &mut self.spare, &epoch::pin(), |(key, val)| {
+            out.push((K::decode(key), val))
+        })
+    }
+
+    /// Returns the smallest key in the queue without removing it.
+    pub fn peek(&mut self) -> Option<K> {
+        self.list.min_key(&epoch::pin()).map(K::decode)
+    }
+}
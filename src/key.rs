@@ -0,0 +1,187 @@
+use std::cmp::Reverse;
+use std::fmt;
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// Unsigned integer type usable as the key of an [`MDList`](crate::MDList).
+///
+/// A key is split into base-`B` digits, one per dimension. When `B` is too
+/// small for the list's dimension to cover every key it may hold, the list
+/// uses the smallest basis that does: with the default `DIMENSION` and
+/// `BASIS`, `u32` keys are split into base-16 digits, `u64` keys into
+/// base-256 ones and `u128` keys into base-65536 ones. The wider the basis,
+/// the longer the chains a search walks in each dimension, so wide keys are
+/// served better by a larger dimension, such as 16 for `u64` and 32 for
+/// `u128`.
+pub trait Key: Copy + Ord + fmt::Debug + Send + Sync + 'static + private::Sealed {
+    /// Width of the key in bits.
+    const BITS: u32;
+    /// The smallest key, zero.
+    const MIN: Self;
+    /// The largest key, with every bit set.
+    const MAX: Self;
+
+    /// Widens the key to a `u128` of the same value.
+    fn to_u128(self) -> u128;
+
+    /// Keeps the low [`BITS`](Key::BITS) bits of `value`.
+    fn from_u128(value: u128) -> Self;
+}
+
+macro_rules! impl_key {
+    ($($t:ty),*) => {$(
+        impl private::Sealed for $t {}
+
+        impl Key for $t {
+            const BITS: u32 = <$t>::BITS;
+            const MIN: Self = <$t>::MIN;
+            const MAX: Self = <$t>::MAX;
+
+            fn to_u128(self) -> u128 {
+                self as u128
+            }
+
+            fn from_u128(value: u128) -> Self {
+                value as $t
+            }
+        }
+
+        impl PriorityKey for $t {
+            type Encoded = $t;
+
+            fn encode(self) -> $t {
+                self
+            }
+
+            fn decode(encoded: $t) -> Self {
+                encoded
+            }
+        }
+    )*};
+}
+
+impl_key!(u8, u16, u32, u64, u128, usize);
+
+/// Priority type accepted by an [`MDList`](crate::MDList), stored as an
+/// unsigned [`Key`] that sorts the same way.
+///
+/// Implementations must keep the order, `a < b` exactly when
+/// `a.encode() < b.encode()`, and `decode` must undo `encode`.
+pub trait PriorityKey: Copy {
+    /// The key type priorities are stored as.
+    type Encoded: Key;
+
+    /// Number of low bits of `Encoded` that encodings can occupy.
+    const BITS: u32 = <Self::Encoded as Key>::BITS;
+
+    /// Maps the priority to its stored key.
+    fn encode(self) -> Self::Encoded;
+    /// Maps a stored key back to the priority it was encoded from.
+    fn decode(encoded: Self::Encoded) -> Self;
+}
+
+// Flipping the sign bit moves negative values below positive ones.
+macro_rules! impl_signed {
+    ($($t:ty => $u:ty),*) => {$(
+        impl PriorityKey for $t {
+            type Encoded = $u;
+
+            fn encode(self) -> $u {
+                (self as $u) ^ (1 << (<$u>::BITS - 1))
+            }
+
+            fn decode(encoded: $u) -> Self {
+                (encoded ^ (1 << (<$u>::BITS - 1))) as $t
+            }
+        }
+    )*};
+}
+
+impl_signed!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize);
+
+// Floats follow `total_cmp`: positive values get the sign bit set, negative
+// ones have every bit inverted. NaNs are kept rather than rejected and sort
+// below negative infinity or above positive infinity depending on their sign.
+macro_rules! impl_float {
+    ($($t:ty => $u:ty),*) => {$(
+        impl PriorityKey for $t {
+            type Encoded = $u;
+
+            fn encode(self) -> $u {
+                const SIGN: $u = 1 << (<$u>::BITS - 1);
+                let bits = self.to_bits();
+                if bits & SIGN == 0 {
+                    bits | SIGN
+                } else {
+                    !bits
+                }
+            }
+
+            fn decode(encoded: $u) -> Self {
+                const SIGN: $u = 1 << (<$u>::BITS - 1);
+                if encoded & SIGN == 0 {
+                    <$t>::from_bits(!encoded)
+                } else {
+                    <$t>::from_bits(encoded & !SIGN)
+                }
+            }
+        }
+    )*};
+}
+
+impl_float!(f32 => u32, f64 => u64);
+
+/// All ones in the low `bits` bits.
+fn mask(bits: u32) -> u128 {
+    u128::MAX >> (128 - bits)
+}
+
+impl<T: PriorityKey> PriorityKey for Reverse<T> {
+    type Encoded = T::Encoded;
+
+    const BITS: u32 = T::BITS;
+
+    fn encode(self) -> T::Encoded {
+        T::Encoded::from_u128(mask(T::BITS) - self.0.encode().to_u128())
+    }
+
+    fn decode(encoded: T::Encoded) -> Self {
+        Reverse(T::decode(T::Encoded::from_u128(
+            mask(T::BITS) - encoded.to_u128(),
+        )))
+    }
+}
+
+// Tuples concatenate the encodings of their fields, first field in the most
+// significant bits, so they compare lexicographically.
+macro_rules! impl_tuple {
+    ($($t:ident $v:ident),+) => {
+        impl<$($t: PriorityKey),+> PriorityKey for ($($t,)+) {
+            type Encoded = u128;
+
+            const BITS: u32 = 0 $(+ $t::BITS)+;
+
+            fn encode(self) -> u128 {
+                const { assert!(Self::BITS <= 128, "tuple keys must fit into 128 bits") };
+                let ($($v,)+) = self;
+                let mut encoded = 0u128;
+                $(encoded = (encoded << $t::BITS) | $v.encode().to_u128();)+
+                encoded
+            }
+
+            fn decode(encoded: u128) -> Self {
+                let mut shift = Self::BITS;
+                $(
+                    shift -= $t::BITS;
+                    let $v = $t::decode($t::Encoded::from_u128((encoded >> shift) & mask($t::BITS)));
+                )+
+                ($($v,)+)
+            }
+        }
+    };
+}
+
+impl_tuple!(A a, B b);
+impl_tuple!(A a, B b, C c);
@@ -0,0 +1,26 @@
+//! Lock-free priority queue built on a multi-dimensional linked list (MDList).
+//!
+//! The queue is exposed through [`MDList`], and as a max-priority queue
+//! through [`MaxMDList`]; everything else in the crate is internal
+//! bookkeeping for the lock-free algorithm.
+
+#![warn(missing_docs, unreachable_pub)]
+
+mod config;
+mod counter;
+mod entry;
+mod error;
+mod handle;
+mod key;
+mod max;
+mod mdlist;
+mod range;
+
+pub use config::{Config, Duplicates};
+pub use entry::EntryHandle;
+pub use error::{BuildError, ConfigError, InsertError};
+pub use handle::Handle;
+pub use key::{Key, PriorityKey};
+pub use max::MaxMDList;
+pub use mdlist::{MDList, ValueRef, BASIS, DIMENSION};
+pub use range::{DrainFilter, Range};
@@ -1,66 +1,55 @@
-mod mdlist;
-
 #[global_allocator]
 static GLOBAL: System = System;
 
 use std::alloc::System;
-use crossbeam::epoch::{self, Atomic};
 use std::sync::Arc;
 use std::thread;
 use std::io::Write;
-use mdlist::{MDList, Stack};
+use lockprio::{Config, MDList};
 use std::sync::atomic::{AtomicU32, AtomicBool, Ordering};
 use std::time::{Instant, Duration};
 
 const DIMENSION: usize = 4;
-const RANGE: usize = 10000;
+const BASIS: u32 = 16;
+const RANGE: u128 = 10000;
 const CONCURRENT_OPS: usize = 1000;
 
+type Queue = MDList<u8, u32, DIMENSION, BASIS>;
+
+fn new_queue() -> Queue {
+    Queue::with_config(Config::new(RANGE)).expect("invalid queue configuration")
+}
+
 fn test_scenario(name: &str, keys: Vec<u32>, values: Vec<u8>) {
     println!("\n=== Testing scenario: {} ===", name);
     std::io::stdout().flush().unwrap();
     
-    let pq = MDList::new(DIMENSION, RANGE);
-    let guard = &epoch::pin();
+    let pq = new_queue();
 
-    for (key, &val) in keys.iter().zip(values.iter()) {
-        pq.insert(*key, Box::into_raw(Box::new(val)) as *mut u8);
+    for (&key, &val) in keys.iter().zip(values.iter()) {
+        pq.insert(key, val).unwrap();
     }
 
     let mut sorted_pairs: Vec<_> = keys.iter().zip(values.iter()).collect();
     sorted_pairs.sort_by_key(|(&k, _)| k);
     let mut expected_order = sorted_pairs.into_iter().map(|(&k, &v)| (k, v)).collect::<Vec<_>>();
 
-    let head_node = pq.head_ptr(guard);
-    let temp_stack = Stack {
-        head: Atomic::from(head_node),
-        del: std::array::from_fn(|_| Atomic::from(head_node)),
-    };
-
     while !expected_order.is_empty() {
-        if let Some(min_node) = pq.delete_min(&temp_stack, guard) {
-            unsafe {
-                let val_ptr = (*min_node.as_raw()).val.load(Ordering::Acquire);
-                if !val_ptr.is_null() {
-                    let val = *val_ptr;
-                    let min_key = (*min_node.as_raw()).key;
-                    
-                    let (expected_key, expected_val) = expected_order.remove(0);
-                    println!("Deleted key: {}, value: {}", expected_key, expected_val);
-                    std::io::stdout().flush().unwrap();
-                    
-                    assert_eq!(min_key, expected_key, 
-                        "Expected key {} but got {}", expected_key, min_key);
-                    assert_eq!(val, expected_val,
-                        "Expected value {} but got {}", expected_val, val);
-                }
-            }
+        if let Some((min_key, val)) = pq.pop_min() {
+            let (expected_key, expected_val) = expected_order.remove(0);
+            println!("Deleted key: {}, value: {}", expected_key, expected_val);
+            std::io::stdout().flush().unwrap();
+
+            assert_eq!(min_key, expected_key,
+                "Expected key {} but got {}", expected_key, min_key);
+            assert_eq!(val, expected_val,
+                "Expected value {} but got {}", expected_val, val);
         } else {
             panic!("Priority queue is empty but expected more elements");
         }
     }
 
-    assert!(pq.delete_min(&temp_stack, guard).is_none(), 
+    assert!(pq.pop_min().is_none(),
         "Queue should be empty after deletions");
     println!("Test passed!");
     std::io::stdout().flush().unwrap();
@@ -75,7 +64,7 @@ fn test_concurrent_producer_consumer() {
     println!("\n=== Testing concurrent producer-consumer (Timeout: {}s) ===", timeout_secs);
     std::io::stdout().flush().unwrap();
 
-    let pq = Arc::new(MDList::new(DIMENSION, RANGE));
+    let pq = Arc::new(new_queue());
 
     let deadlock_flag = deadlock_detected.clone();
     thread::spawn({
@@ -91,14 +80,13 @@ fn test_concurrent_producer_consumer() {
             let ops_counter = ops_counter.clone();
             let deadlock_detected = deadlock_detected.clone();
             move || {
-                let guard = &epoch::pin();
                 for i in 0..CONCURRENT_OPS {
                     if deadlock_detected.load(Ordering::SeqCst) {
                         println!("[PRODUCER] Deadlock detected - aborting");
                         return;
                     }
                     
-                    pq.insert(i as u32, Box::into_raw(Box::new(i as u8)) as *mut u8);
+                    pq.insert(i as u32, i as u8).unwrap();
                     ops_counter.fetch_add(1, Ordering::Relaxed);
                     
                     if i % 100 == 0 {
@@ -116,13 +104,7 @@ fn test_concurrent_producer_consumer() {
             let ops_counter = ops_counter.clone();
             let deadlock_detected = deadlock_detected.clone();
             move || {
-                let guard = &epoch::pin();
                 let mut count = 0;
-                let head = pq.head_ptr(guard);
-                let temp_stack = Stack {
-                    head: Atomic::from(head),
-                    del: std::array::from_fn(|_| Atomic::from(head)),
-                };
 
                 while count < CONCURRENT_OPS {
                     if deadlock_detected.load(Ordering::SeqCst) {
@@ -130,20 +112,15 @@ fn test_concurrent_producer_consumer() {
                         return;
                     }
 
-                    if let Some(min_node) = pq.delete_min(&temp_stack, guard) {
-                        unsafe {
-                            let val_ptr = (*min_node.as_raw()).val.load(Ordering::Acquire);
-                            if !val_ptr.is_null() {
-                                count += 1;
-                                ops_counter.fetch_add(1, Ordering::Relaxed);
-                                
-                                if count % 100 == 0 {
-                                    println!("[CONSUMER] Deleted {} (Total: {}) - Elapsed: {:.2}s",
-                                        count, ops_counter.load(Ordering::Relaxed),
-                                        test_start.elapsed().as_secs_f32());
-                                    std::io::stdout().flush().unwrap();
-                                }
-                            }
+                    if pq.pop_min().is_some() {
+                        count += 1;
+                        ops_counter.fetch_add(1, Ordering::Relaxed);
+
+                        if count % 100 == 0 {
+                            println!("[CONSUMER] Deleted {} (Total: {}) - Elapsed: {:.2}s",
+                                count, ops_counter.load(Ordering::Relaxed),
+                                test_start.elapsed().as_secs_f32());
+                            std::io::stdout().flush().unwrap();
                         }
                     }
                 }
@@ -194,7 +171,7 @@ fn test_concurrent_mixed_ops() {
     println!("\n=== Testing concurrent mixed operations (Timeout: {}s) ===", timeout_secs);
     std::io::stdout().flush().unwrap();
 
-    let pq = Arc::new(MDList::new(DIMENSION, RANGE));
+    let pq = Arc::new(new_queue());
 
     let deadlock_flag = deadlock_detected.clone();
     thread::spawn({
@@ -211,13 +188,6 @@ fn test_concurrent_mixed_ops() {
                 let ops_counter = ops_counter.clone();
                 let deadlock_detected = deadlock_detected.clone();
                 move || {
-                    let guard = &epoch::pin();
-                    let head = pq.head_ptr(guard);
-                    let temp_stack = Stack {
-                        head: Atomic::from(head),
-                        del: std::array::from_fn(|_| Atomic::from(head)),
-                    };
-
                     for i in 0..CONCURRENT_OPS/4 {
                         if deadlock_detected.load(Ordering::SeqCst) {
                             println!("[THREAD {}] Deadlock detected - aborting", thread_id);
@@ -225,18 +195,11 @@ fn test_concurrent_mixed_ops() {
                         }
 
                         let key = (thread_id * 1000) + i as u32;
-                        pq.insert(key, Box::into_raw(Box::new(i as u8)) as *mut u8);
+                        pq.insert(key, i as u8).unwrap();
                         ops_counter.fetch_add(1, Ordering::Relaxed);
 
-                        if i % 5 == 0 {
-                            if let Some(min_node) = pq.delete_min(&temp_stack, guard) {
-                                unsafe {
-                                    let val_ptr = (*min_node.as_raw()).val.load(Ordering::Acquire);
-                                    if !val_ptr.is_null() {
-                                        ops_counter.fetch_add(1, Ordering::Relaxed);
-                                    }
-                                }
-                            }
+                        if i % 5 == 0 && pq.pop_min().is_some() {
+                            ops_counter.fetch_add(1, Ordering::Relaxed);
                         }
 
                         if i % 50 == 0 {
@@ -334,19 +297,13 @@ mod tests {
     
     #[test]
     fn basic_test() {
-        let pq = MDList::new(4, 10000); // Use your dimensions
-        let guard = crossbeam::epoch::pin();
+        let pq = MDList::new(10000); // Use your dimensions
         
         // Insert one item
-        pq.insert(1, Box::into_raw(Box::new(1)) as *mut u8);
+        pq.insert(1, 1u8).unwrap();
         
         // Try to remove it
-        let head = pq.head_ptr(&guard);
-        let stack = Stack {
-            head: Atomic::from(head),
-            del: std::array::from_fn(|_| Atomic::from(head)),
-        };
-        assert!(pq.delete_min(&stack, &guard).is_some());
+        assert_eq!(pq.pop_min(), Some((1, 1)));
     }
 }
 
@@ -355,20 +312,14 @@ fn two_thread_test() {
     use std::sync::Arc;
     use std::thread;
 
-    let pq = Arc::new(MDList::new(4, 10000));
-    let guard = crossbeam::epoch::pin();
+    let pq = Arc::new(MDList::new(10000));
 
     let pq_clone = pq.clone();
     let inserter = thread::spawn(move || {
-        pq_clone.insert(1, Box::into_raw(Box::new(1)) as *mut u8);
+        pq_clone.insert(1, 1u8).unwrap();
     });
 
-    let head = pq.head_ptr(&guard);
-    let stack = Stack {
-        head: Atomic::from(head),
-        del: std::array::from_fn(|_| Atomic::from(head)),
-    };
-    let _ = pq.delete_min(&stack, &guard);
+    let _ = pq.pop_min();
 
     inserter.join().unwrap();
 }
@@ -378,28 +329,22 @@ fn three_thread_test() {
     use std::sync::Arc;
     use std::thread;
 
-    let pq = Arc::new(MDList::new(4, 10000));
-    let guard = crossbeam::epoch::pin();
+    let pq = Arc::new(MDList::new(10000));
 
     // Thread 1: Inserter
     let pq1 = pq.clone();
     let t1 = thread::spawn(move || {
-        pq1.insert(1, Box::into_raw(Box::new(1)) as *mut u8);
+        pq1.insert(1, 1u8).unwrap();
     });
 
     // Thread 2: Inserter  
     let pq2 = pq.clone();
     let t2 = thread::spawn(move || {
-        pq2.insert(2, Box::into_raw(Box::new(2)) as *mut u8);
+        pq2.insert(2, 2u8).unwrap();
     });
 
     // Thread 3: Remover
-    let head = pq.head_ptr(&guard);
-    let stack = Stack {
-        head: Atomic::from(head),
-        del: std::array::from_fn(|_| Atomic::from(head)),
-    };
-    let _ = pq.delete_min(&stack, &guard);
+    let _ = pq.pop_min();
 
     t1.join().unwrap();
     t2.join().unwrap();
@@ -409,42 +354,29 @@ fn three_thread_test() {
 fn four_thread_test() {
     use std::sync::Arc;
     use std::thread;
-    use crossbeam::epoch;
 
-    let pq = Arc::new(MDList::new(4, 10000));
+    let pq = Arc::new(MDList::new(10000));
     
     // Two inserters
     let pq1 = pq.clone();
     let t1 = thread::spawn(move || {
-        pq1.insert(1, Box::into_raw(Box::new(1)) as *mut u8);
+        pq1.insert(1, 1u8).unwrap();
     });
     
     let pq2 = pq.clone();
     let t2 = thread::spawn(move || {
-        pq2.insert(2, Box::into_raw(Box::new(2)) as *mut u8);
+        pq2.insert(2, 2u8).unwrap();
     });
 
     // Two deleters
     let pq3 = pq.clone();
     let t3 = thread::spawn(move || {
-        let guard = epoch::pin();
-        let head = pq3.head_ptr(&guard);
-        let stack = Stack {
-            head: Atomic::from(head),
-            del: std::array::from_fn(|_| Atomic::from(head)),
-        };
-        pq3.delete_min(&stack, &guard);
+        let _ = pq3.pop_min();
     });
 
     let pq4 = pq.clone();
     let t4 = thread::spawn(move || {
-        let guard = epoch::pin();
-        let head = pq4.head_ptr(&guard);
-        let stack = Stack {
-            head: Atomic::from(head),
-            del: std::array::from_fn(|_| Atomic::from(head)),
-        };
-        pq4.delete_min(&stack, &guard);
+        let _ = pq4.pop_min();
     });
 
     t1.join().unwrap();
@@ -457,16 +389,15 @@ fn four_thread_test() {
 fn six_thread_test() {
     use std::sync::Arc;
     use std::thread;
-    use crossbeam::epoch;
 
-    let pq = Arc::new(MDList::new(4, 10000));
+    let pq = Arc::new(MDList::new(10000));
     
     // Three inserters
     let insert_values = vec![1, 2, 3];
     let insert_threads: Vec<_> = insert_values.into_iter().map(|val| {
         let pq = pq.clone();
         thread::spawn(move || {
-            pq.insert(val, Box::into_raw(Box::new(val)) as *mut u8);
+            pq.insert(val, val as u8).unwrap();
         })
     }).collect();
 
@@ -474,13 +405,7 @@ fn six_thread_test() {
     let delete_threads: Vec<_> = (0..3).map(|_| {
         let pq = pq.clone();
         thread::spawn(move || {
-            let guard = epoch::pin();
-            let head = pq.head_ptr(&guard);
-            let stack = Stack {
-                head: Atomic::from(head),
-                del: std::array::from_fn(|_| Atomic::from(head)),
-            };
-            pq.delete_min(&stack, &guard)
+            let _ = pq.pop_min();
         })
     }).collect();
 
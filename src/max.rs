@@ -0,0 +1,128 @@
+use crate::config::Config;
+use crate::error::{ConfigError, InsertError};
+use crate::key::{Key, PriorityKey};
+use crate::mdlist::{MDList, ValueRef, BASIS, DIMENSION};
+
+/// Max-priority queue: an [`MDList`] whose coordinate mapping is reversed,
+/// so that the deletion cursor walks from the largest key down.
+///
+/// Keys are mirrored within the accepted range before they are stored: with
+/// a range of `n`, key `k` is kept as `n - 1 - k`, which reverses every
+/// base-`B` digit of its coordinates. `pop_max` therefore has the cost of
+/// [`MDList::pop_min`], while `pop_min` is the descent of
+/// [`MDList::pop_max`].
+pub struct MaxMDList<V, K: PriorityKey = u32, const D: usize = DIMENSION, const B: u32 = BASIS> {
+    list: MDList<V, K, D, B>,
+    /// Largest key accepted, as encoded.
+    top: u128,
+}
+
+impl<V> MaxMDList<V> {
+    /// Creates a queue of `u32` keys below `range` with the default
+    /// dimension and basis. Panics like [`MDList::new`].
+    pub fn new(range: u128) -> Self {
+        match Self::with_config(Config::new(range)) {
+            Ok(list) => list,
+            Err(e) => panic!("invalid MDList configuration: {}", e),
+        }
+    }
+}
+
+impl<V, K: PriorityKey, const D: usize, const B: u32> MaxMDList<V, K, D, B> {
+
+    /// Creates a queue from `config`. Fails like [`MDList::with_config`].
+    pub fn with_config(config: Config) -> Result<Self, ConfigError> {
+        let list = MDList::with_config(config)?;
+        let top = match config.range {
+            // An empty range accepts no key, so nothing is ever mirrored.
+            Some(range) => range.saturating_sub(1),
+            None => K::Encoded::MAX.to_u128(),
+        };
+        Ok(MaxMDList { list, top })
+    }
+
+    /// Maps a key to the one stored in its place, and back.
+    fn mirror(&self, key: K) -> K {
+        K::decode(K::Encoded::from_u128(self.top - key.encode().to_u128()))
+    }
+
+    /// Inserts `val` under `key`. Fails like [`MDList::insert`].
+    pub fn insert(&self, key: K, val: V) -> Result<(), InsertError> {
+        self.list.check_key(key.encode())?;
+        self.list.insert(self.mirror(key), val)
+    }
+
+    /// Removes the entry with the largest key and returns it.
+    pub fn pop_max(&self) -> Option<(K, V)> {
+        let max = self.list.pop_min();
+        max.map(|(key, val)| (self.mirror(key), val))
+    }
+
+    /// Removes up to `k` entries with the largest keys and appends them to
+    /// `out`, largest first. See [`MDList::pop_min_n`].
+    pub fn pop_max_n(&self, k: usize, out: &mut Vec<(K, V)>) -> usize {
+        let start = out.len();
+        let popped = self.list.pop_min_n(k, out);
+        for (key, _) in &mut out[start..] {
+            *key = self.mirror(*key);
+        }
+        popped
+    }
+
+    /// Removes the entry with the smallest key and returns it. See
+    /// [`MDList::pop_max`].
+    pub fn pop_min(&self) -> Option<(K, V)> {
+        let min = self.list.pop_max();
+        min.map(|(key, val)| (self.mirror(key), val))
+    }
+
+    /// Returns the largest key in the queue without removing its entry.
+    pub fn peek_max_key(&self) -> Option<K> {
+        self.list.peek_min_key().map(|key| self.mirror(key))
+    }
+
+    /// Returns the entry `pop_max` would remove, with its value borrowed.
+    /// See [`MDList::peek_min`].
+    pub fn peek_max(&self) -> Option<(K, ValueRef<'_, V>)>
+    where
+        V: Clone + Sync,
+    {
+        let max = self.list.peek_min();
+        max.map(|(key, val)| (self.mirror(key), val))
+    }
+
+    /// Removes the oldest entry under `key` and returns its value.
+    pub fn remove(&self, key: K) -> Option<V> {
+        self.list.check_key(key.encode()).ok()?;
+        self.list.remove(self.mirror(key))
+    }
+
+    /// Whether any entry is stored under `key`.
+    pub fn contains(&self, key: K) -> bool {
+        self.list.check_key(key.encode()).is_ok() && self.list.contains(self.mirror(key))
+    }
+
+    /// Borrows the oldest value stored under `key`. See [`MDList::get`].
+    pub fn get(&self, key: K) -> Option<ValueRef<'_, V>>
+    where
+        V: Clone + Sync,
+    {
+        self.list.check_key(key.encode()).ok()?;
+        self.list.get(self.mirror(key))
+    }
+
+    /// Returns the number of entries. See [`MDList::len`].
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    /// Whether the queue holds no entries. See [`MDList::is_empty`].
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    /// Removes and drops every entry.
+    pub fn clear(&self) {
+        self.list.clear();
+    }
+}
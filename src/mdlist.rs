@@ -1,762 +1,2420 @@
-    use crossbeam::epoch::{self, Atomic, Owned, Shared, Guard};
-    use std::sync::atomic::{AtomicU32, Ordering}; 
-    use crossbeam::epoch::CompareExchangeError;
-    use std::sync::atomic::{AtomicBool, AtomicPtr};
-    use std::sync::Arc;
-    use std::ops::Deref;
+use crossbeam::epoch::{self, Atomic, Guard, Owned, Shared};
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::RangeBounds;
+use std::ptr;
+use std::sync::OnceLock;
+use std::sync::atomic::{fence, AtomicBool, AtomicPtr, AtomicU32, AtomicU8, AtomicUsize, Ordering};
+
+use crate::config::{Config, Duplicates};
+use crate::counter::StripedCounter;
+use crate::range::{DrainFilter, Range};
+use crate::entry::EntryHandle;
+use crate::error::{BuildError, ConfigError, InsertError};
+use crate::handle::Handle;
+use crate::key::{Key, PriorityKey};
+
+/// Default number of coordinate dimensions.
+pub const DIMENSION: usize = 8;
+/// Default basis of each coordinate. Keys that do not fit into `DIMENSION`
+/// digits of it are taken in a wider basis.
+pub const BASIS: u32 = 16;
+const CACHE_LINE_SIZE: usize = 64;
+
+/// Source of the ids that tie entry handles to the list they came from.
+static NEXT_LIST_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Set on a child link once its subtree has been adopted by a newer node.
+const FADP: usize = 0b01;
+/// Set on a child link once its node belongs to a prefix being purged.
+const FPRG: usize = 0b10;
+/// Set on a value pointer that points to a `Move` rather than a `Slot`.
+const MOVE: usize = 0b1;
+
+/// States of a `Move`.
+const PENDING: u8 = 0;
+const COMMITTED: u8 = 1;
+const ABORTED: u8 = 2;
+
+struct Desc<V, K, const D: usize, const B: u32> {
+    curr: Atomic<Node<V, K, D, B>>,
+    dp: u8,
+    dc: u8,
+}
+
+struct Node<V, K, const D: usize, const B: u32> {
+    child: [Atomic<Node<V, K, D, B>>; D],
+    key: K,
+    coord: [u32; D],
+    /// Insertion stamp; values under equal keys are popped in `seq` order,
+    /// as compared by `seq_before`.
+    seq: u32,
+    /// Set once a purge has frozen the node to cut it off the list.
+    purged: AtomicBool,
+    pending: Atomic<Desc<V, K, D, B>>,
+    /// Value owned by the node; null once the node has been deleted. Holds
+    /// a tagged `Move` while the value moves into or out of the node.
+    val: AtomicPtr<Slot<V>>,
+    /// Moves out of the node that have not finished yet. Their descriptors
+    /// point at the node, so a purge leaves it in place until they are done.
+    moving: AtomicUsize,
+    /// Next node inserted with the same key; the chain of them runs from
+    /// the oldest to the newest.
+    dup: Atomic<Node<V, K, D, B>>,
+    /// On the node linked into the list, the first node of its duplicate
+    /// chain that may still hold a value, or null while that is the node
+    /// itself. Emptied nodes before it are retired.
+    front: Atomic<Node<V, K, D, B>>,
+    /// On the node linked into the list, the last node of its duplicate
+    /// chain or one shortly before it, or null while that is the node
+    /// itself. It never falls behind `front`.
+    back: Atomic<Node<V, K, D, B>>,
+}
+
+/// A value stored in the list, boxed together with a count of its owners.
+struct Slot<V> {
+    /// One for the list, from the insertion until a claim releases the
+    /// value, plus one per [`ValueRef`] to it. The last owner drops the
+    /// value, or in the case of the list moves it out.
+    refs: AtomicUsize,
+    /// Clones `val` for a claim that finds it borrowed. Only borrowers need
+    /// `V: Clone`, so the first one sets it.
+    clone: OnceLock<fn(&V) -> V>,
+    /// Stamp of the insertion that stored the value, which its entry handle
+    /// names it by. Unlike the stamp of the node, it stays with the value
+    /// when the value moves, and a value replacing it gets its own.
+    seq: u32,
+    val: V,
+}
 
-    static GLOBAL_SEQ: AtomicU32 = AtomicU32::new(1);
+/// A value moving from one node to another for `change_priority`. The node
+/// it moves into is linked holding the tagged descriptor first. The node it
+/// moves out of is then locked by swapping the descriptor in for the value,
+/// and the move committed. Any thread that runs into either node can take
+/// these steps, and it puts the value in its new place before it goes on.
+struct Move<V, K, const D: usize, const B: u32> {
+    from: *const Node<V, K, D, B>,
+    /// The node the value moves into; null until it is linked.
+    to: AtomicPtr<Node<V, K, D, B>>,
+    slot: *mut Slot<V>,
+    state: AtomicU8,
+}
+
+/// Deletion cursor shared by every thread calling `pop_min`. `del` holds the
+/// cursor for the list rooted at `head`; everything before
+/// `del[D - 1]` in key order has been deleted.
+pub(crate) struct Stack<V, K, const D: usize, const B: u32> {
+    head: Atomic<Node<V, K, D, B>>,
+    del: [Atomic<Node<V, K, D, B>>; D],
+}
 
-    pub trait AtomicMarking<T> {
-        fn load_marked<'g>(&self, guard: &'g Guard) -> Shared<'g, T>;
+/// Lock-free priority queue of values of type `V` under priorities of type
+/// `K`, smallest first.
+///
+/// Keys are split into `D` coordinates of base `B`, and nodes are linked so
+/// that a walk in key order visits them in coordinate order. Deletions mark
+/// values taken and leave their nodes in place; once enough have piled up,
+/// the emptied prefix of the list is cut off in one step. Every method takes
+/// `&self` and may be called from any number of threads at once.
+pub struct MDList<V, K: PriorityKey = u32, const D: usize = DIMENSION, const B: u32 = BASIS> {
+    /// Exclusive upper bound of the keys accepted by `insert`, if narrower
+    /// than the key type.
+    range: Option<u128>,
+    /// Basis keys are split into coordinates in: `B`, unless `D` digits of
+    /// it cannot hold every key accepted.
+    basis: u64,
+    head: Atomic<Node<V, K::Encoded, D, B>>,
+    _pad: [u8; CACHE_LINE_SIZE - std::mem::size_of::<usize>()],
+    stack: Atomic<Stack<V, K::Encoded, D, B>>,
+    _pad1: [u8; CACHE_LINE_SIZE - std::mem::size_of::<usize>()],
+    /// Bumped after every insertion so that a deleter can tell whether a node
+    /// may have landed behind the cursor it just published.
+    inserted: AtomicUsize,
+    /// Source of the insertion stamps of this list's nodes.
+    seq: AtomicU32,
+    /// Tells the entry handles of this list apart from those of others.
+    id: usize,
+    /// Entries inserted minus entries removed, for `len`.
+    len: StripedCounter,
+    /// Deletions since the last purge.
+    marked_node: AtomicU32,
+    /// Number of deletions after which the deleted prefix is purged.
+    r: u32,
+    purging: AtomicBool,
+    duplicates: Duplicates,
+    /// Never linked and never given children; a cursor pointing at it in
+    /// some dimension has nothing left to visit there.
+    leaf: Box<Node<V, K::Encoded, D, B>>,
+    _marker: PhantomData<Box<V>>,
+}
 
-        fn compare_and_set_marked<'g>(
-            &self,
-            current: Shared<'g, T>,
-            new: Shared<'g, T>,
-            guard: &'g Guard,
-        ) -> Result<Shared<'g, T>, CompareExchangeError<'g, T, Shared<'g, T>>>;
+// Values are moved between threads through `insert` and `pop_min`, which
+// only needs `V: Send`. Methods that let several threads see a value at once,
+// cloning it or passing it to a predicate, additionally require `V: Sync`.
+unsafe impl<V: Send, K: PriorityKey, const D: usize, const B: u32> Send for MDList<V, K, D, B> {}
+unsafe impl<V: Send, K: PriorityKey, const D: usize, const B: u32> Sync for MDList<V, K, D, B> {}
+
+impl<V, K: Key, const D: usize, const B: u32> Node<V, K, D, B> {
+    fn new(key: K, coord: [u32; D], val: *mut Slot<V>, seq: u32) -> Self {
+        Node {
+            child: array_init::array_init(|_| Atomic::null()),
+            key,
+            coord,
+            seq,
+            purged: AtomicBool::new(false),
+            pending: Atomic::null(),
+            val: AtomicPtr::new(val),
+            moving: AtomicUsize::new(0),
+            dup: Atomic::null(),
+            front: Atomic::null(),
+            back: Atomic::null(),
+        }
     }
 
-impl<T> AtomicMarking<T> for Atomic<T> {
-    fn load_marked<'g>(&self, guard: &'g Guard) -> Shared<'g, T> {
-        self.load(Ordering::Acquire, guard)
+    fn clone_without_children(&self) -> Self {
+        Node::new(self.key, self.coord, ptr::null_mut(), self.seq)
     }
 
-    fn compare_and_set_marked<'g>(
-        &self,
-        current: Shared<'g, T>,
-        new: Shared<'g, T>,
-        guard: &'g Guard,
-    ) -> Result<Shared<'g, T>, CompareExchangeError<'g, T, Shared<'g, T>>> {
-        self.compare_exchange(current, new, Ordering::AcqRel, Ordering::Acquire, guard)
+    fn new_fdel(seq: u32) -> Self {
+        Node::new(K::MIN, [0; D], ptr::null_mut(), seq)
     }
 }
 
+impl<V, K, const D: usize, const B: u32> Node<V, K, D, B> {
+    /// Frees a descriptor left behind by a failed insertion attempt. Only
+    /// valid while the node is still private to the inserting thread.
+    fn reset_pending(&self, guard: &Guard) {
+        let desc = self.pending.swap(Shared::null(), Ordering::Relaxed, guard);
+        if !desc.is_null() {
+            drop(unsafe { desc.into_owned() });
+        }
+    }
 
-pub const DIMENSION: usize = 8;
-const CACHE_LINE_SIZE: usize = 64;
-
-const FADP: usize = 0b001;
-const FPRG: usize = 0b010;
-const FDEL: usize = 0b100;
+    /// Completes the adoption of children this node's insertion may have
+    /// left pending, so that its links in the adopted dimensions can be read.
+    fn finish_inserting(&self, guard: &Guard) {
+        let desc_ptr = self.pending.load(Ordering::Acquire, guard);
+        if desc_ptr.is_null() {
+            return;
+        }
 
-const ADPINV_MASK: usize = 1;
-const PRGINV_MASK: usize = 2;
-const INVALID_MASK: usize = 3;
-const MARKED_MASK: usize = 1;
-const DELETED_MASK: usize = 1;
+        let desc = unsafe { desc_ptr.deref() };
+        let curr = unsafe { desc.curr.load(Ordering::Acquire, guard).deref() };
 
-pub struct Desc {
-    curr: Atomic<Node>,
-    dp: u8,      
-    dc: u8,          
-}
+        for i in desc.dp as usize..desc.dc as usize {
+            let child = curr.child[i].fetch_or(FADP, Ordering::AcqRel, guard);
+            let child = clear_mark(child, FADP | FPRG);
+            let _ = self.child[i].compare_exchange(
+                Shared::null(),
+                child,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+                guard,
+            );
+        }
 
-pub struct Node {
-    pub child: [Atomic<Node>; DIMENSION],
-    pub key: u32,
-    pub coord: [u32; DIMENSION],
-    pub seq: u32,
-    pub purged: Atomic<Node>,
-    pub pending: Atomic<Desc>,
-    pub val: AtomicPtr<u8>,
-}
+        if self
+            .pending
+            .compare_exchange(
+                desc_ptr,
+                Shared::null(),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+                guard,
+            )
+            .is_ok()
+        {
+            unsafe { guard.defer_destroy(desc_ptr) };
+        }
+    }
 
+    /// Whether this node itself holds the value stamped `seq`.
+    fn holds(&self, seq: u32) -> bool {
+        let val = self.load_val();
+        !val.is_null() && unsafe { (*val).seq } == seq
+    }
 
-pub struct HeadNode {
-    pub node: Arc<Node>,
-    pub ver: AtomicU32,
-}
+    /// Whether any value is still stored under this node's key.
+    fn has_value(&self, guard: &Guard) -> bool {
+        self.any_in_chain(guard, |node| !node.load_val().is_null())
+    }
 
-pub struct PriorityQueue {
-    pub d: usize,
-    pub n: usize,
-    pub r: usize,
-    pub head: Arc<HeadNode>,
-    pub stack: Arc<Stack>,
-}
+    /// Whether nothing at all is stored under this node's key, not even a
+    /// value moving in or out, so that a purge may cut the node.
+    fn is_clear(&self, guard: &Guard) -> bool {
+        !self.any_in_chain(guard, |node| {
+            !node.val.load(Ordering::SeqCst).is_null() || node.moving.load(Ordering::SeqCst) != 0
+        })
+    }
 
-#[derive(Clone)]
-pub struct Stack {
-    pub head: Atomic<Node>,
-    pub del: [Atomic<Node>; DIMENSION],
-}
+    fn any_in_chain(&self, guard: &Guard, f: impl FnMut(&Self) -> bool) -> bool {
+        self.chain(guard).any(f)
+    }
 
-pub struct MDList {
-    dimension: usize, 
-    range: usize,    
-    head: Atomic<Node>,
-    _pad: [u8; CACHE_LINE_SIZE - std::mem::size_of::<Atomic<Node>>()],
-    stack: Atomic<Stack>,
-    _pad1: [u8; CACHE_LINE_SIZE - std::mem::size_of::<Atomic<Shared<Stack>>>()],
-    purge: Atomic<Stack>,
-    marked_node: AtomicU32,
-    r: u32,
-    purging: AtomicBool,
-}
+    /// The nodes under this node's key that may still hold a value, oldest
+    /// first.
+    fn chain<'a>(&'a self, guard: &'a Guard) -> impl Iterator<Item = &'a Self> + 'a {
+        let front = self.front.load(Ordering::Acquire, guard);
+        let first = unsafe { front.as_ref() }.unwrap_or(self);
+        std::iter::successors(Some(first), move |node| unsafe {
+            node.dup.load(Ordering::Acquire, guard).as_ref()
+        })
+    }
 
-impl Node {
-    pub fn new(key: u32, coord: [u32; DIMENSION], val: Option<*mut u8>, seq: u32) -> Self {
-    Node {
-        child: array_init::array_init(|_| Atomic::null()),
-        key,
-        coord,
-        seq,
-        purged: Atomic::null(),
-        pending: Atomic::null(),
-        val: AtomicPtr::new(val.unwrap_or(std::ptr::null_mut())),
+    /// Moves the front of the duplicate chain past `curr`, which holds no
+    /// value and never will again, to `next`, and retires `curr` unless it
+    /// is this node itself.
+    fn pass_front<'g>(
+        &self,
+        curr: Shared<'g, Self>,
+        next: Shared<'g, Self>,
+        guard: &'g Guard,
+    ) {
+        // An append reads the back without the front to guard it, so it has
+        // to move on before the node it may point to is retired.
+        let _ = self.back.compare_exchange(curr, next, Ordering::AcqRel, Ordering::Acquire, guard);
+        if self
+            .front
+            .compare_exchange(curr, next, Ordering::AcqRel, Ordering::Acquire, guard)
+            .is_ok()
+            && !curr.is_null()
+        {
+            unsafe {
+                guard.defer_unchecked(move || {
+                    // The rest of the chain is still in use.
+                    let node = curr.into_owned();
+                    node.dup.store(Shared::null(), Ordering::Relaxed);
+                    drop(node);
+                })
+            };
+        }
     }
+
+    /// The value stored on this node alone, or null if it has none. A move
+    /// the node takes part in is settled first.
+    fn load_val(&self) -> *mut Slot<V> {
+        loop {
+            let val = self.val.load(Ordering::SeqCst);
+            if !is_move(val) {
+                return val;
+            }
+            unsafe { Move::<V, K, D, B>::from_tag(val) }.help(self);
+        }
     }
 
-    pub fn clone_without_children(&self) -> Self {
-    Node {
-        child: array_init::array_init(|_| Atomic::null()),
-        key: self.key,
-        coord: self.coord,
-        seq: self.seq,
-        purged: Atomic::null(),
-        pending: Atomic::null(),
-        val: AtomicPtr::new(self.val.load(std::sync::atomic::Ordering::Relaxed)),
+    /// Takes back what an insertion stored on this node, unless it has been
+    /// claimed since. A move that took the place of the value is left alone.
+    fn take_back(&self, stored: *mut Slot<V>) -> Option<*mut Slot<V>> {
+        loop {
+            let val = self.val.load(Ordering::SeqCst);
+            if val.is_null() || (is_move(val) && val != stored) {
+                return None;
+            }
+            if self
+                .val
+                .compare_exchange(val, ptr::null_mut(), Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+            {
+                return Some(val);
+            }
+        }
     }
+
+    /// The node in the duplicate chain holding the oldest live value. The
+    /// emptied nodes in front of it are unlinked on the way: a node is only
+    /// ever emptied once, so the front can move past them for good.
+    fn oldest<'a>(&'a self, guard: &'a Guard) -> Option<&'a Self> {
+        let mut curr = self.front.load(Ordering::Acquire, guard);
+        loop {
+            let node = unsafe { curr.as_ref() }.unwrap_or(self);
+            if !node.load_val().is_null() {
+                return Some(node);
+            }
+            // The last node stays in the chain for appends to link to.
+            let next = node.dup.load(Ordering::Acquire, guard);
+            if next.is_null() {
+                return None;
+            }
+            self.pass_front(curr, next, guard);
+            curr = next;
+        }
     }
-    
 
-    pub fn new_fdel(seq: u32) -> Self {
-    Node {
-        child: array_init::array_init(|_| Atomic::null()),
-        key: 0,
-        coord: [0; DIMENSION],
-        seq,
-        purged: Atomic::null(),
-        pending: Atomic::null(),
-        val: AtomicPtr::new(std::ptr::null_mut()),
+    /// Claims the oldest value stored under this node's key, if any is left,
+    /// together with its insertion stamp.
+    fn take_value(&self, guard: &Guard) -> Option<(u32, V)> {
+        loop {
+            // Another thread may claim the value first; look again.
+            let node = self.oldest(guard)?;
+            if let Some(val) = node.take_val(guard) {
+                return Some((node.seq, val));
+            }
+        }
     }
+
+    /// Claims the oldest value stored under this node's key only if `pred`
+    /// accepts it.
+    fn take_value_if(&self, guard: &Guard, pred: &mut Check<'_, V>) -> Claim<V> {
+        loop {
+            let Some(node) = self.oldest(guard) else {
+                return Claim::Empty;
+            };
+            // Another thread may claim the value first; look again.
+            match node.take_val_if(pred, guard) {
+                Claim::Empty => continue,
+                claim => return claim,
+            }
+        }
     }
-}
 
-impl MDList {
-    pub fn head_ptr<'g>(&self, guard: &'g crossbeam::epoch::Guard) -> crossbeam::epoch::Shared<'g, Node> {
-        self.head.load(std::sync::atomic::Ordering::Acquire, guard)
+    /// Claims the oldest value stored under this node's key that `pred`
+    /// accepts, among those stamped after `after`, together with its stamp.
+    fn take_first_if(
+        &self,
+        after: Option<u32>,
+        guard: &Guard,
+        pred: &mut Check<'_, V>,
+    ) -> Option<(u32, V)> {
+        let mut nodes: Vec<_> = self
+            .chain(guard)
+            .filter(|node| after.is_none_or(|after| seq_before(after, node.seq)))
+            .collect();
+        nodes.sort_by(|a, b| seq_cmp(a.seq, b.seq));
+        nodes.into_iter().find_map(|node| match node.take_val_if(pred, guard) {
+            Claim::Taken(val) => Some((node.seq, val)),
+            Claim::Empty | Claim::Rejected => None,
+        })
     }
-}
 
+    /// Claims the value of this node alone if `pred` accepts it. `pred`
+    /// runs while the value stays claimable by others, and the claim only
+    /// succeeds if the value it was offered is still in place afterwards.
+    fn take_val_if(&self, pred: &mut Check<'_, V>, guard: &Guard) -> Claim<V> {
+        loop {
+            let val = self.load_val();
+            if val.is_null() {
+                return Claim::Empty;
+            }
+            // The slot is only freed once this thread unpins, so its address
+            // cannot come back with another value before the exchange below.
+            let accepted = pred(Offer {
+                node: &self.val,
+                slot: val,
+                _list: PhantomData,
+            });
+            if !accepted && self.val.load(Ordering::SeqCst) == val {
+                return Claim::Rejected;
+            }
+            // Otherwise the value was taken or replaced; check what is there now.
+            if accepted
+                && self
+                    .val
+                    .compare_exchange(val, ptr::null_mut(), Ordering::SeqCst, Ordering::SeqCst)
+                    .is_ok()
+            {
+                return match Slot::release(val, guard) {
+                    Some(val) => Claim::Taken(val),
+                    None => unreachable!(),
+                };
+            }
+        }
+    }
 
-impl MDList {
-    pub fn new(dimension: usize, range: usize) -> Self {
-        let coord = [0u32; DIMENSION];
-        let guard = &crossbeam::epoch::pin();
-    
-        let head_node = Node::new(0, coord.clone(), None, dimension as u32);
-        let head_shared = Owned::new(head_node).into_shared(guard);
-        let head_atomic = Atomic::from(head_shared);
-    
-        let owned_stack = Owned::new(Stack {
-            head: Atomic::from(head_shared),
-            del: std::array::from_fn(|_| Atomic::from(head_shared)),
-        });
-        let shared_stack = owned_stack.into_shared(guard);
-    
-    let mdlist = MDList {
-        head: head_atomic,
-        _pad: [0u8; CACHE_LINE_SIZE - std::mem::size_of::<Atomic<Node>>()],
-        stack: Atomic::null(),
-        _pad1: [0u8; CACHE_LINE_SIZE - std::mem::size_of::<Atomic<Shared<Stack>>>()],
-        purge: Atomic::null(),
-        marked_node: AtomicU32::new(0),
-        r: 0,
-        purging: AtomicBool::new(false),
-        dimension,
-        range,
-    };
-    
-        mdlist.stack.store(shared_stack, Ordering::Release);
-    
-        mdlist
-    }    
+    /// Claims the value of this node alone, ignoring its duplicates.
+    fn take_val(&self, guard: &Guard) -> Option<V> {
+        loop {
+            let val = self.load_val();
+            if val.is_null() {
+                return None;
+            }
+            if self
+                .val
+                .compare_exchange(val, ptr::null_mut(), Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+            {
+                return Slot::release(val, guard);
+            }
+        }
+    }
 }
 
+impl<V: Clone + Sync, K, const D: usize, const B: u32> Node<V, K, D, B> {
+    /// Borrows the oldest value stored under this node's key, if any is left.
+    fn peek_value<'a>(&self, guard: &Guard) -> Option<ValueRef<'a, V>> {
+        loop {
+            let node = self.oldest(guard)?;
+            if let Some(val) = node.borrow_val() {
+                return Some(val);
+            }
+        }
+    }
 
-pub fn set_adpinv<'g, T>(ptr: Shared<'g, T>) -> Shared<'g, T> {
-    ptr.with_tag(ptr.tag() | ADPINV_MASK)
-}
+    /// Clones every value stored under this node's key, oldest first.
+    fn clone_values(&self, guard: &Guard) -> Vec<V> {
+        let borrowed = self.chain(guard).filter_map(|node| node.borrow_val());
+        borrowed.map(|val| V::clone(&val)).collect()
+    }
 
-pub fn clr_adpinv<'g, T>(ptr: Shared<'g, T>) -> Shared<'g, T> {
-    ptr.with_tag(ptr.tag() & !ADPINV_MASK)
+    fn borrow_val<'a>(&self) -> Option<ValueRef<'a, V>> {
+        let val = self.load_val();
+        if val.is_null() {
+            return None;
+        }
+        Slot::borrow(&self.val, val)
+    }
 }
 
-pub fn is_adpinv<'g, T>(ptr: Shared<'g, T>) -> bool {
-    ptr.tag() & ADPINV_MASK != 0
-}
+impl<V> Slot<V> {
+    fn boxed(val: V, seq: u32) -> *mut Self {
+        Box::into_raw(Box::new(Slot {
+            refs: AtomicUsize::new(1),
+            clone: OnceLock::new(),
+            seq,
+            val,
+        }))
+    }
 
+    /// Unboxes a slot that was never shared.
+    fn into_inner(slot: *mut Self) -> V {
+        unsafe { Box::from_raw(slot) }.val
+    }
 
-pub fn set_prginv<'g>(ptr: Shared<'g, Node>) -> Shared<'g, Node> {
-    ptr.with_tag(ptr.tag() | PRGINV_MASK)
-}
+    /// Takes ownership of a value just unlinked from the list. If it is
+    /// borrowed, the claim gets a clone instead of waiting for the borrows
+    /// to end, and the last of them drops the value. The slot itself is
+    /// freed only when every thread that may still compare against its
+    /// address has unpinned.
+    fn release(slot: *mut Self, guard: &Guard) -> Option<V> {
+        let refs = &unsafe { slot.as_ref() }?.refs;
+        if refs.compare_exchange(1, 0, Ordering::SeqCst, Ordering::SeqCst).is_ok() {
+            let val = unsafe { ptr::read(&(*slot).val) };
+            Self::retire(slot, guard);
+            return Some(val);
+        }
+        // Released even if the clone panics.
+        let owned = Share(slot);
+        let slot = unsafe { &*slot };
+        // The borrower that raised the count set `clone` before.
+        let clone = slot.clone.get().expect("borrowed value without a clone function");
+        let val = clone(&slot.val);
+        drop(owned);
+        Some(val)
+    }
 
-pub fn clr_prginv<'g>(ptr: Shared<'g, Node>) -> Shared<'g, Node> {
-    ptr.with_tag(ptr.tag() & !PRGINV_MASK)
-}
+    /// Drops one owner of the value, and the value with the last one.
+    fn unref(slot: *mut Self, guard: &Guard) {
+        if unsafe { &*slot }.refs.fetch_sub(1, Ordering::SeqCst) == 1 {
+            unsafe { ptr::drop_in_place(&mut (*slot).val) };
+            Self::retire(slot, guard);
+        }
+    }
 
-pub fn is_prginv<'g>(ptr: Shared<'g, Node>) -> bool {
-    ptr.tag() & PRGINV_MASK != 0
+    /// Frees a slot whose value has been moved out or dropped.
+    fn retire(slot: *mut Self, guard: &Guard) {
+        let slot = slot.cast::<std::mem::ManuallyDrop<Self>>();
+        unsafe { guard.defer_unchecked(move || drop(Box::from_raw(slot))) };
+    }
 }
 
-pub fn clr_invalid<'g>(ptr: Shared<'g, Node>) -> Shared<'g, Node> {
-    ptr.with_tag(ptr.tag() & !INVALID_MASK)
+impl<V: Clone + Sync> Slot<V> {
+    /// Borrows the value of `slot`, unless it has left `node` since it was
+    /// read from there. The slot is freed only after the caller unpins, so
+    /// its count can be touched even if the value has just been claimed.
+    fn borrow<'a>(node: &AtomicPtr<Self>, slot: *mut Self) -> Option<ValueRef<'a, V>> {
+        let inner = unsafe { &*slot };
+        inner.clone.get_or_init(|| V::clone);
+        let mut refs = inner.refs.load(Ordering::SeqCst);
+        loop {
+            // The last owner is gone, and the value with it.
+            if refs == 0 {
+                return None;
+            }
+            match inner.refs.compare_exchange(refs, refs + 1, Ordering::SeqCst, Ordering::SeqCst) {
+                Ok(_) => break,
+                Err(now) => refs = now,
+            }
+        }
+        let borrowed = ValueRef {
+            slot,
+            _list: PhantomData,
+        };
+        // A claim may have taken the value in the meantime; the borrow
+        // then keeps it alive but no longer shows an entry of the list.
+        (node.load(Ordering::SeqCst) == slot).then_some(borrowed)
+    }
 }
 
-pub fn is_invalid<'g>(ptr: Shared<'g, Node>) -> bool {
-    ptr.tag() & INVALID_MASK != 0
-}
+/// One owner's share of a slot, given up when dropped.
+struct Share<V>(*mut Slot<V>);
 
-pub fn set_marked<'g>(ptr: Shared<'g, Stack>) -> Shared<'g, Stack> {
-    ptr.with_tag(ptr.tag() | MARKED_MASK)
+impl<V> Drop for Share<V> {
+    fn drop(&mut self) {
+        Slot::unref(self.0, &epoch::pin());
+    }
 }
 
-pub fn clr_marked<'g>(ptr: Shared<'g, Stack>) -> Shared<'g, Stack> {
-    ptr.with_tag(ptr.tag() & !MARKED_MASK)
+/// A value borrowed from an [`MDList`], returned by [`MDList::get`] and
+/// [`MDList::peek_min`]. It keeps the value alive, not in the list: a pop
+/// that claims the entry meanwhile takes a clone of the value rather than
+/// wait for the borrow to end, and the value is dropped with the last
+/// borrow.
+pub struct ValueRef<'a, V> {
+    slot: *mut Slot<V>,
+    _list: PhantomData<&'a V>,
 }
 
-pub fn set_deleted<'g>(ptr: Shared<'g, Node>) -> Shared<'g, Node> {
-    ptr.with_tag(ptr.tag() | DELETED_MASK)
-}
+// A borrow reads the value from the thread holding it, and the last one may
+// drop it there.
+unsafe impl<V: Sync> Sync for ValueRef<'_, V> {}
+unsafe impl<V: Send + Sync> Send for ValueRef<'_, V> {}
 
-pub fn clr_deleted<'g>(ptr: Shared<'g, Node>) -> Shared<'g, Node> {
-    ptr.with_tag(ptr.tag() & !DELETED_MASK)
-}
+impl<V> std::ops::Deref for ValueRef<'_, V> {
+    type Target = V;
 
-pub fn is_deleted<'g>(ptr: Shared<'g, Node>) -> bool {
-    ptr.tag() & DELETED_MASK != 0
+    fn deref(&self) -> &V {
+        unsafe { &(*self.slot).val }
+    }
 }
 
-pub fn set_mark_ptr<'g, T>(ptr: Shared<'g, T>, mark: usize) -> Shared<'g, T> {
-    ptr.with_tag(ptr.tag() | mark)
+impl<V: fmt::Debug> fmt::Debug for ValueRef<'_, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
 }
 
-pub fn clear_mark<'g, T>(ptr: Shared<'g, T>, mark: usize) -> Shared<'g, T> {
-    ptr.with_tag(ptr.tag() & !mark)
+impl<V> Drop for ValueRef<'_, V> {
+    fn drop(&mut self) {
+        Slot::unref(self.slot, &epoch::pin());
+    }
 }
 
-pub fn is_marked<'g, T>(ptr: Shared<'g, T>, mark: usize) -> bool {
-    ptr.tag() & mark != 0
+fn is_move<V>(val: *mut Slot<V>) -> bool {
+    val as usize & MOVE != 0
 }
 
-
-pub fn key_to_coord(key: u32) -> [u32; DIMENSION] {
-    const BASIS: u32 = 16; 
-    let mut coord = [0u32; DIMENSION];
-    let mut quotient = key;
-
-    for i in (0..DIMENSION).rev() {
-        coord[i] = quotient % BASIS;
-        quotient /= BASIS;
+impl<V, K, const D: usize, const B: u32> Move<V, K, D, B> {
+    /// The descriptor as stored in place of a value. Its alignment leaves
+    /// the low bit of its address free, as does that of a `Slot`.
+    fn tag(&self) -> *mut Slot<V> {
+        (self as *const Self as usize | MOVE) as *mut Slot<V>
     }
 
-    coord
-}
-
-impl MDList {
-    fn next_seq() -> u32 {
-        GLOBAL_SEQ.fetch_add(1, Ordering::Relaxed)
+    /// # Safety
+    ///
+    /// `val` was read from a node while pinned, and the descriptor is only
+    /// freed once every thread that may have read it has unpinned.
+    unsafe fn from_tag<'a>(val: *mut Slot<V>) -> &'a Self {
+        &*((val as usize & !MOVE) as *const Self)
     }
 
-pub fn insert(&self, key: u32, val: *mut u8) {
-    let guard = &epoch::pin();
-    let coord = key_to_coord(key);
-    let new_node = Owned::new(Node::new(key, coord, Some(val), Self::next_seq()));
-    let new_ptr = new_node.into_shared(guard);
-    
-    loop {
-        let mut pred = Shared::null();
-        let mut curr = Shared::null();
-        let mut dp = 0;
-        let mut dc = 0;
-        let mut stack = Stack {
-            head: Atomic::null(),
-            del: std::array::from_fn(|_| Atomic::null()),
+    /// Settles the move for a thread that found it stored on `at`. Once
+    /// the new node is known the move is completed, so that the value is
+    /// in place there before the thread moves on; until then it is called
+    /// off, and the mover tries again.
+    fn help(&self, at: &Node<V, K, D, B>) {
+        let to = self.to.load(Ordering::SeqCst);
+        let state = if ptr::eq(at, self.from) || ptr::eq(at, to) {
+            self.complete()
+        } else if to.is_null() {
+            self.abort()
+        } else {
+            self.state.load(Ordering::SeqCst)
         };
-
-        self.locate_pred(&coord, &mut pred, &mut curr, &mut dp, &mut dc, &mut stack, guard);
-
-    unsafe {
-        let mut prev = self.head.load(Ordering::Acquire, guard);
-        let mut next = prev.deref().child[0].load(Ordering::Acquire, guard);
-        
-        while !next.is_null() && next.deref().key < key {
-            prev = next;
-            next = next.deref().child[0].load(Ordering::Acquire, guard);
+        self.finish(state);
+        if state == ABORTED && !ptr::eq(at, self.from) {
+            self.settle(at, ptr::null_mut());
         }
-        
-        new_ptr.deref().child[0].store(next, Ordering::Release);
-        prev.deref().child[0].store(new_ptr, Ordering::Release);
     }
 
-    for d in 1..DIMENSION {
-        unsafe {
-            new_ptr.deref().child[d].store(
-                stack.del[d].load(Ordering::Acquire, guard),
-                Ordering::Release
-            );
-
-            if d == dp && !pred.is_null() {
-                pred.deref().child[d].store(new_ptr, Ordering::Release);
-            } else {
-                self.head.load(Ordering::Acquire, guard)
-                    .deref()
-                    .child[d]
-                    .store(new_ptr, Ordering::Release);
+    /// Locks `from` on the value the move started with and commits, or
+    /// aborts once `from` holds anything else.
+    fn complete(&self) -> u8 {
+        let from = unsafe { &*self.from };
+        loop {
+            let state = self.state.load(Ordering::SeqCst);
+            if state != PENDING {
+                return state;
+            }
+            match from
+                .val
+                .compare_exchange(self.slot, self.tag(), Ordering::SeqCst, Ordering::SeqCst)
+            {
+                Ok(_) => {}
+                Err(val) if val == self.tag() => {}
+                // Another move of the value, which may yet be called off.
+                Err(val) if is_move(val) && from.load_val() == self.slot => continue,
+                Err(_) => {
+                    self.abort();
+                    continue;
+                }
             }
+            let _ = self.state.compare_exchange(PENDING, COMMITTED, Ordering::SeqCst, Ordering::SeqCst);
         }
     }
 
-    self.rewind_stack(key, dc, pred, &stack, guard);
-    break;
-    }
+    /// Calls the move off unless it has been committed, and returns the
+    /// outcome.
+    fn abort(&self) -> u8 {
+        match self.state.compare_exchange(PENDING, ABORTED, Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(_) => ABORTED,
+            Err(state) => state,
+        }
     }
-    
-}
 
-    
-impl MDList {
-    pub fn locate_pred<'g>(
-        &self,
-        coord: &[u32; DIMENSION],
-        pred: &mut Shared<'g, Node>,
-        curr: &mut Shared<'g, Node>,
-        dp: &mut usize,
-        dc: &mut usize,
-        stack: &Stack,
-        guard: &'g Guard,
-    ) {
-        *curr = self.head.load(Ordering::Acquire, guard);
-        *dc = 0;
-        *dp = 0;
-    
-        while *dc < DIMENSION {
-            if *dc >= DIMENSION {
-                break;
-            }
-    
-            while !curr.is_null() {
-                let curr_node = unsafe { &*curr.as_raw() };
-    
-                if curr_node.coord[*dc] < coord[*dc] {
-                    *pred = *curr;
-                    *dp = *dc;
-                    self.finish_inserting(*curr, *dc, *dc, guard);
-                    *curr = curr_node.child[*dc].load(Ordering::Acquire, guard);
-                } else {
-                    break;
-                }
-            }
-    
-            if curr.is_null() {
-                break;
-            }
-    
-            let curr_node = unsafe { &*curr.as_raw() };
-    
-            if curr_node.coord[*dc] > coord[*dc] {
-                *dc = DIMENSION;
-                break;
-            } 
-            else if curr_node.coord[*dc] == coord[*dc] {
-                if *dc < DIMENSION {
-                    unsafe {
-                        stack.del[*dc].store(*curr, Ordering::Relaxed);
-                    }
-                }
-                *dc += 1;
-            }
-            else {
-                *pred = *curr;
-                *dp = *dc;
-                self.finish_inserting(*curr, *dc, *dc, guard);
-                *curr = curr_node.child[*dc].load(Ordering::Acquire, guard);
+    /// Puts the value in its new place once the move is committed, or back
+    /// in its old one once it is aborted. The descriptor is cleared off the
+    /// new node of an aborted move by whoever finds it there.
+    fn finish(&self, state: u8) {
+        let from = unsafe { &*self.from };
+        match state {
+            COMMITTED => {
+                // `to` is set before anyone may lock `from`, and holds the
+                // descriptor until the first call gets here.
+                self.settle(unsafe { &*self.to.load(Ordering::SeqCst) }, self.slot);
+                self.settle(from, ptr::null_mut());
             }
+            ABORTED => self.settle(from, self.slot),
+            _ => {}
         }
-    
-        *dp = (*dp).min(DIMENSION - 1);
+    }
+
+    fn settle(&self, node: &Node<V, K, D, B>, val: *mut Slot<V>) {
+        let _ = node.val.compare_exchange(self.tag(), val, Ordering::SeqCst, Ordering::SeqCst);
     }
 }
 
+/// A value a conditional claim is about to take, as shown to its check.
+pub(crate) struct Offer<'a, V> {
+    /// Where the value is stored.
+    node: &'a AtomicPtr<Slot<V>>,
+    slot: *mut Slot<V>,
+    _list: PhantomData<&'a V>,
+}
 
-impl MDList {
-    pub fn fill_new_node<'g>(
-        &self,
-        node_ptr: Shared<'g, Node>,
-        curr: Shared<'g, Node>,
-        dp: usize,
-        dc: usize,
-        guard: &'g Guard,
-    ) {
-        let node = unsafe { node_ptr.deref() };
-        node.pending.store(Shared::null(), Ordering::Relaxed);
+impl<'a, V: Clone + Sync> Offer<'a, V> {
+    /// Borrows the value on offer, unless it has left its place since.
+    pub(crate) fn borrow(&self) -> Option<ValueRef<'a, V>> {
+        Slot::borrow(self.node, self.slot)
+    }
+}
 
-        if dp < dc {
-            let desc = Desc {
-                curr: Atomic::from(curr),
-                dp: dp as u8,
-                dc: dc as u8,
-            };
-            node.pending.store(Owned::new(desc).into_shared(guard), Ordering::Release);
+impl<V, K, const D: usize, const B: u32> Drop for Node<V, K, D, B> {
+    fn drop(&mut self) {
+        // The node is unreachable by now, so its descriptor and duplicate
+        // chain can be freed without waiting for another epoch. The nodes
+        // in front of the chain have been retired already.
+        let guard = unsafe { epoch::unprotected() };
+        let val = *self.val.get_mut();
+        debug_assert!(!is_move(val));
+        if !val.is_null() {
+            // Borrows of the value may outlive the node.
+            Slot::unref(val, guard);
         }
-
-        for i in 0..dp {
-            node.child[i].store(Shared::null(), Ordering::Relaxed);
+        self.reset_pending(guard);
+        let mut dup = self.dup.swap(Shared::null(), Ordering::Relaxed, guard);
+        let front = self.front.load(Ordering::Relaxed, guard);
+        if !front.is_null() {
+            dup = front;
         }
-
-        for i in dp..DIMENSION {
-            node.child[i].store(Shared::null(), Ordering::Relaxed);
+        while !dup.is_null() {
+            let node = unsafe { dup.into_owned() };
+            dup = node.dup.swap(Shared::null(), Ordering::Relaxed, guard);
         }
+    }
+}
 
-        if dc < DIMENSION {
-            node.child[dc].store(curr, Ordering::Relaxed);
+/// Whether stamp `a` was handed out before `b`. Stamps wrap around, so this
+/// holds as long as the two are less than `2^31` insertions apart.
+fn seq_before(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) < 0
+}
+
+/// Orders stamps the way `seq_before` does.
+fn seq_cmp(a: u32, b: u32) -> std::cmp::Ordering {
+    (a.wrapping_sub(b) as i32).cmp(&0)
+}
+
+impl<V, K: Key, const D: usize, const B: u32> Stack<V, K, D, B> {
+    fn at(head: Shared<'_, Node<V, K, D, B>>) -> Self {
+        Stack {
+            head: Atomic::from(head),
+            del: std::array::from_fn(|_| Atomic::from(head)),
         }
     }
 }
 
+/// Position of a traversal in key order. `del[d]` is the node whose
+/// `child[d]` leads to the next unvisited subtree in dimension `d`.
+struct Cursor<'g, V, K, const D: usize, const B: u32> {
+    del: [Shared<'g, Node<V, K, D, B>>; D],
+}
 
-impl MDList {
-    pub fn finish_inserting<'g>(
-        &self,
-        node: Shared<'g, Node>,
-        dp: usize,
-        dc: usize,
-        guard: &'g Guard,
-    ) {
-        if node.is_null() {
-            return;
-        }
+/// The node holding a key after an insertion, and the node holding the
+/// value inserted, which is a duplicate of the first one in a bag.
+type Inserted<'g, V, K, const D: usize, const B: u32> =
+    (Shared<'g, Node<V, K, D, B>>, Shared<'g, Node<V, K, D, B>>);
 
-        let n = unsafe { node.deref() };
-        let desc_ptr = n.pending.load(Ordering::Acquire, guard);
-        if desc_ptr.is_null() {
-            return;
-        }
+/// Why an insertion failed, and the value it was given.
+type Refused<V> = (InsertError, *mut Slot<V>);
 
-        let desc = unsafe { desc_ptr.deref() };
+/// A node a search can start at, and the dimension to continue in.
+type Resume<'g, V, K, const D: usize, const B: u32> = (Shared<'g, Node<V, K, D, B>>, usize);
 
-        if dc < desc.dp as usize || dp > desc.dc as usize {
-            return;
-        }
+/// Check a conditional claim runs on the value it is offered.
+type Check<'f, V> = dyn FnMut(Offer<'_, V>) -> bool + 'f;
 
-        let curr_ptr = desc.curr.load(Ordering::Acquire, guard);
-        if curr_ptr.is_null() {
-            return;
-        }
-        let curr = unsafe { curr_ptr.deref() };
+/// Check a conditional deletion runs on each entry it is offered.
+type Filter<'f, V, K> = dyn FnMut(K, Offer<'_, V>) -> bool + 'f;
 
-        for i in desc.dp as usize..desc.dc as usize {
-            let mut child = curr.child[i].load(Ordering::Acquire, guard);
+/// Outcome of a conditional claim on a node.
+enum Claim<T> {
+    Taken(T),
+    /// No value is left under the node's key.
+    Empty,
+    /// The oldest value left was turned down by the predicate.
+    Rejected,
+}
 
-        while !is_adpinv(child) && !is_prginv(child) {
-            let new_ptr = set_adpinv(child);
-            let result = curr.child[i].compare_exchange(
-                child,
-                new_ptr,
-                Ordering::AcqRel,
-                Ordering::Acquire,
-                guard,
-            );
-            match result {
-                Ok(_) => break,
-                Err(e) => {
-                    child = e.current;
-                }
-            }
-        }
+enum Advance<'g, V, K, const D: usize, const B: u32> {
+    Node(Shared<'g, Node<V, K, D, B>>),
+    End,
+    /// A link frozen by an adoption was read; the traversal has to restart.
+    Stale,
+}
 
-        let clean_child = clr_adpinv(child);
-        let _ = n.child[i].compare_exchange(
-            Shared::null(),
-            clean_child,
-            Ordering::AcqRel,
-            Ordering::Acquire,
-            guard,
-        );
+impl<'g, V, K: Key, const D: usize, const B: u32> Cursor<'g, V, K, D, B> {
+    fn at(node: Shared<'g, Node<V, K, D, B>>) -> Self {
+        Cursor { del: [node; D] }
     }
 
-    let _ = n.pending.compare_exchange(
-        desc_ptr,
-        Shared::null(),
-        Ordering::AcqRel,
-        Ordering::Acquire,
-        guard,
-    );
+    fn load(stack: &Stack<V, K, D, B>, guard: &'g Guard) -> Self {
+        Cursor {
+            del: std::array::from_fn(|d| stack.del[d].load(Ordering::Acquire, guard)),
+        }
     }
-}
-
 
+    /// The node visited last.
+    fn position(&self) -> Shared<'g, Node<V, K, D, B>> {
+        self.del[D - 1]
+    }
 
+    fn to_stack(&self, head: Shared<'g, Node<V, K, D, B>>) -> Stack<V, K, D, B> {
+        Stack {
+            head: Atomic::from(head),
+            del: std::array::from_fn(|d| Atomic::from(self.del[d])),
+        }
+    }
 
-impl MDList {
-    pub fn rewind_stack<'g>(
-        &self,
-        key: u32,
-        dp: usize,
-        pred: Shared<'g, Node>,
-        stack: &Stack,
-        guard: &'g Guard,
-    ) {
-    let mut old_shared = self.stack.load(Ordering::Acquire, guard);
-    let dp = dp.min(DIMENSION - 1); 
+    fn advance(&mut self, guard: &'g Guard) -> Advance<'g, V, K, D, B> {
+        for d in (0..D).rev() {
+            // A link still waiting for its adopted subtree would skip it.
+            let node = unsafe { self.del[d].deref() };
+            node.finish_inserting(guard);
+            let child = node.child[d].load(Ordering::Acquire, guard);
+            if is_marked(child, FADP) {
+                return Advance::Stale;
+            }
+            let child = clear_mark(child, FPRG);
+            if !child.is_null() {
+                for del in &mut self.del[d..] {
+                    *del = child;
+                }
+                return Advance::Node(child);
+            }
+        }
+        Advance::End
+    }
+}
 
-    loop {
-        let old = unsafe { old_shared.deref() };
 
-        let old_head_ptr = old.head.load(Ordering::Acquire, guard);
-        if old_head_ptr.is_null() {
-            break;
+impl<V> MDList<V> {
+    /// Creates a list of `u32` keys below `range` with the default dimension
+    /// and basis. Use [`with_config`](MDList::with_config) to pick other ones.
+    pub fn new(range: u128) -> Self {
+        match Self::with_config(Config::new(range)) {
+            Ok(mdlist) => mdlist,
+            Err(e) => panic!("invalid MDList configuration: {}", e),
         }
+    }
+}
 
-        let old_head_node = unsafe { old_head_ptr.deref() };
-        let old_head_seq = old_head_node.seq;
+impl<V, K: PriorityKey, const D: usize, const B: u32> MDList<V, K, D, B> {
+    /// Creates an empty list from `config`. Fails if the dimension or basis
+    /// is unusable, if the purge threshold is zero, or if the keys accepted
+    /// do not fit into `D` coordinates in any basis.
+    pub fn with_config(config: Config) -> Result<Self, ConfigError> {
+        Self::validate_shape()?;
+        config.validate()?;
+        let basis = Self::basis_for(config.range)?;
+        let Config {
+            range,
+            purge_threshold,
+            duplicates,
+        } = config;
+        let guard = &epoch::pin();
+
+        let head = Owned::new(Node::new_fdel(0)).into_shared(guard);
+
+        Ok(MDList {
+            head: Atomic::from(head),
+            _pad: [0u8; CACHE_LINE_SIZE - std::mem::size_of::<usize>()],
+            stack: Atomic::new(Stack::at(head)),
+            _pad1: [0u8; CACHE_LINE_SIZE - std::mem::size_of::<usize>()],
+            inserted: AtomicUsize::new(0),
+            seq: AtomicU32::new(1),
+            id: NEXT_LIST_ID.fetch_add(1, Ordering::Relaxed),
+            len: StripedCounter::new(),
+            marked_node: AtomicU32::new(0),
+            r: purge_threshold,
+            purging: AtomicBool::new(false),
+            duplicates,
+            leaf: Box::new(Node::new_fdel(0)),
+            _marker: PhantomData,
+            range,
+            basis,
+        })
+    }
 
-        let new_stack = Stack {
-            head: Atomic::from(old_head_ptr),
-            del: std::array::from_fn(|i| old.del[i].clone()),
+    /// The smallest basis of at least `B` whose `D` digits hold every key
+    /// below `range`, or every key of the key type. Coordinates are `u32`s,
+    /// so no basis can be wider than `2^32`.
+    fn basis_for(range: Option<u128>) -> Result<u64, ConfigError> {
+        const WIDEST: u64 = 1 << 32;
+        let fits = |basis: u64| {
+            // `None` when the coordinates hold more keys than fit into a `u128`.
+            let capacity = (basis as u128).checked_pow(D as u32);
+            match (range, capacity) {
+                (_, None) => true,
+                (Some(range), Some(capacity)) => range <= capacity,
+                (None, Some(capacity)) => K::BITS < 128 && capacity >> K::BITS != 0,
+            }
         };
-
-        let last_del = old.del[DIMENSION - 1].load(Ordering::Acquire, guard);
-        if last_del.is_null() {
-            break;
+        if !fits(WIDEST) {
+            let capacity = (WIDEST as u128).pow(D as u32);
+            return Err(match range {
+                Some(range) => ConfigError::RangeTooLarge { range, capacity },
+                None => ConfigError::KeyTooWide {
+                    bits: K::BITS,
+                    capacity,
+                },
+            });
         }
-        let last_key = unsafe { last_del.deref().key };
-
-        let current_head_ptr = new_stack.head.load(Ordering::Acquire, guard);
-        if current_head_ptr.is_null() {
-            break;
+        // The least basis that fits, found by bisection.
+        let (mut lo, mut hi) = (B as u64, WIDEST);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if fits(mid) {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
         }
-        let current_head_seq = unsafe { current_head_ptr.deref().seq };
+        Ok(lo)
+    }
 
-        if current_head_seq == old_head_seq {
-            if key <= last_key {
-                for i in dp..DIMENSION {
-                    if i >= DIMENSION { break; }  
-                    stack.del[i].store(pred, Ordering::Relaxed);
-                }
-            } else {
+    /// Builds a list from entries sorted by key, linking every node directly
+    /// instead of searching for its place. Keys that arrive out of order are
+    /// inserted the regular way, as is everything after them.
+    pub fn from_sorted<I>(config: Config, iter: I) -> Result<Self, BuildError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mdlist = Self::with_config(config)?;
+        let guard = &epoch::pin();
+
+        // `path[d]` is the node whose `child[d]` takes the next key if that
+        // key first differs from the last one in dimension `d`.
+        let mut path = [mdlist.head.load(Ordering::Relaxed, guard); D];
+        let mut iter = iter.into_iter();
+        let mut added = 0;
+        for (key, val) in iter.by_ref() {
+            let key = key.encode();
+            mdlist.check_key(key)?;
+            let last = unsafe { path[D - 1].deref() };
+            if key < last.key {
+                mdlist.insert_with(key, val, guard)?;
                 break;
             }
-        } else if current_head_seq > old_head_seq {
-            let prg_ptr = clear_mark(old_head_node.purged.load(Ordering::Acquire, guard), FPRG);
-            if !prg_ptr.is_null() {
-                let prg_node = unsafe { prg_ptr.deref() };
-                if prg_node.key <= last_key {
-                    let new_head = clear_mark(prg_node.purged.load(Ordering::Acquire, guard), FPRG);
-                    if !new_head.is_null() {
-                        new_stack.head.store(new_head, Ordering::Relaxed);
-                        for i in 0..DIMENSION {
-                            stack.del[i].store(new_head, Ordering::Relaxed);
-                        }
+
+            let coord = key_to_coord::<K::Encoded, D>(key, mdlist.basis);
+            let seq = mdlist.next_seq();
+            let node = Owned::new(Node::new(key, coord, Slot::boxed(val, seq), seq));
+            match (0..D).find(|&d| coord[d] != last.coord[d]) {
+                Some(d) => {
+                    // As in `fill_new_node`, the dimensions below `d` belong
+                    // to the predecessor.
+                    for i in 0..d {
+                        node.child[i].store(set_mark_ptr(Shared::null(), FADP), Ordering::Relaxed);
                     }
-                } else {
-                    break;
+                    let node = node.into_shared(guard);
+                    unsafe { path[d].deref() }.child[d].store(node, Ordering::Relaxed);
+                    path[d..].fill(node);
                 }
-            } else {
-                break;
-            }
-        } else {
-            let prg_ptr = clear_mark(old_head_node.purged.load(Ordering::Acquire, guard), FPRG);
-            if !prg_ptr.is_null() {
-                let prg_node = unsafe { prg_ptr.deref() };
-                if prg_node.key <= key {
-                    let new_head = clear_mark(prg_node.purged.load(Ordering::Acquire, guard), FPRG);
-                    if !new_head.is_null() {
-                        new_stack.head.store(new_head, Ordering::Relaxed);
-                        for i in 0..DIMENSION {
-                            stack.del[i].store(new_head, Ordering::Relaxed);
-                        }
+                None if mdlist.duplicates == Duplicates::Keep => {
+                    mdlist.push_duplicate(path[D - 1], node, guard);
+                }
+                None => {
+                    let stored = mdlist.store_value(path[D - 1], node, mdlist.duplicates, guard);
+                    let replaced = stored.map_err(|val| {
+                        drop(Slot::into_inner(val));
+                        InsertError::DuplicateKey { key: key.to_u128() }
+                    })?;
+                    if replaced {
+                        continue;
                     }
-                } else {
-                    for i in dp..DIMENSION {
-                        if i >= DIMENSION { break; }
-                        stack.del[i].store(pred, Ordering::Relaxed);
+                }
+            }
+            added += 1;
+        }
+        mdlist.len.add(added);
+
+        mdlist.insert_many(iter)?;
+        Ok(mdlist)
+    }
+
+    fn validate_shape() -> Result<(), ConfigError> {
+        // Adoption descriptors store dimensions as `u8`.
+        if D == 0 || D > u8::MAX as usize {
+            return Err(ConfigError::InvalidDimension {
+                dimension: D,
+                max: u8::MAX as usize,
+            });
+        }
+        if B < 2 {
+            return Err(ConfigError::InvalidBasis { basis: B });
+        }
+        Ok(())
+    }
+}
+
+
+fn set_mark_ptr<T>(ptr: Shared<'_, T>, mark: usize) -> Shared<'_, T> {
+    ptr.with_tag(ptr.tag() | mark)
+}
+
+fn clear_mark<T>(ptr: Shared<'_, T>, mark: usize) -> Shared<'_, T> {
+    ptr.with_tag(ptr.tag() & !mark)
+}
+
+fn is_marked<T>(ptr: Shared<'_, T>, mark: usize) -> bool {
+    ptr.tag() & mark != 0
+}
+
+
+/// The key right after `key`, unless it is the largest one.
+pub(crate) fn next_key<K: Key>(key: K) -> Option<K> {
+    (key != K::MAX).then(|| K::from_u128(key.to_u128() + 1))
+}
+
+fn key_to_coord<K: Key, const D: usize>(key: K, basis: u64) -> [u32; D] {
+    let mut coord = [0u32; D];
+    let mut quotient = key.to_u128();
+
+    for i in (0..D).rev() {
+        coord[i] = (quotient % basis as u128) as u32;
+        quotient /= basis as u128;
+    }
+
+    coord
+}
+
+impl<V, K: PriorityKey, const D: usize, const B: u32> MDList<V, K, D, B> {
+    fn next_seq(&self) -> u32 {
+        self.seq.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Inserts `val` under `key`. Fails if `key` lies outside the range the
+    /// list was configured with, or if it is already present and the list
+    /// was configured with [`Duplicates::Reject`].
+    pub fn insert(&self, key: K, val: V) -> Result<(), InsertError> {
+        let key = key.encode();
+        self.check_key(key)?;
+        self.insert_with(key, val, &epoch::pin())
+    }
+
+    pub(crate) fn check_key(&self, key: K::Encoded) -> Result<(), InsertError> {
+        match self.range {
+            Some(range) if key.to_u128() >= range => Err(InsertError::KeyOutOfRange {
+                key: key.to_u128(),
+                range,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns a handle that keeps a spare deletion stack across pops. Like
+    /// the list's own methods, each of its operations pins the thread only
+    /// for as long as it runs.
+    pub fn handle(&self) -> Handle<'_, V, K, D, B> {
+        Handle::new(self)
+    }
+
+    pub(crate) fn insert_with(&self, key: K::Encoded, val: V, guard: &Guard) -> Result<(), InsertError> {
+        self.insert_slot(key, val, None, guard).map(|_| ())
+    }
+
+    /// Inserts `val` under `key` with a fresh stamp, dropping it if the key
+    /// is rejected.
+    fn insert_slot<'g>(
+        &self,
+        key: K::Encoded,
+        val: V,
+        hint: Option<Shared<'g, Node<V, K::Encoded, D, B>>>,
+        guard: &'g Guard,
+    ) -> Result<Inserted<'g, V, K::Encoded, D, B>, InsertError> {
+        let seq = self.next_seq();
+        let inserted = self.insert_stamped(key, seq, Slot::boxed(val, seq), hint, self.duplicates, guard);
+        inserted.map_err(|(e, val)| {
+            drop(Slot::into_inner(val));
+            e
+        })
+    }
+
+    /// Inserts `val` under `key` and returns a handle to the new entry, for
+    /// use with [`change_priority`](MDList::change_priority). Fails like
+    /// [`insert`](MDList::insert).
+    pub fn insert_entry(&self, key: K, val: V) -> Result<EntryHandle<K>, InsertError> {
+        let encoded = key.encode();
+        self.check_key(encoded)?;
+        // The stamp of the value, not that of its node: in a set, the node
+        // may be one an earlier entry under `key` was stored on.
+        let guard = &epoch::pin();
+        let seq = self.next_seq();
+        let inserted = self.insert_stamped(encoded, seq, Slot::boxed(val, seq), None, self.duplicates, guard);
+        if let Err((e, val)) = inserted {
+            drop(Slot::into_inner(val));
+            return Err(e);
+        }
+        Ok(EntryHandle {
+            list: self.id,
+            key,
+            seq,
+        })
+    }
+
+    /// Inserts every entry of `iter` while pinned once. Each insertion starts
+    /// its search at the node of the previous key when that one is smaller,
+    /// so sorted input skips most of the walk from the head. Stops at the
+    /// first key that cannot be inserted; the entries before it stay in.
+    pub fn insert_many<I: IntoIterator<Item = (K, V)>>(&self, iter: I) -> Result<(), InsertError> {
+        let guard = &epoch::pin();
+        let mut hint = None;
+        for (key, val) in iter {
+            let key = key.encode();
+            self.check_key(key)?;
+            hint = Some(self.insert_slot(key, val, hint, guard)?.0);
+        }
+        Ok(())
+    }
+
+    /// Inserts `val`, a boxed value or a tagged `Move`, under `key` with the
+    /// stamp `seq`. The search may start at `hint`, a node returned by an
+    /// earlier insertion under the same guard. Returns the node now holding
+    /// `key` and the node holding `val`, or `val` if `duplicates` rejected
+    /// it. A moving value is counted by `len` where it came from.
+    fn insert_stamped<'g>(
+        &self,
+        key: K::Encoded,
+        seq: u32,
+        mut val: *mut Slot<V>,
+        mut hint: Option<Shared<'g, Node<V, K::Encoded, D, B>>>,
+        duplicates: Duplicates,
+        guard: &'g Guard,
+    ) -> Result<Inserted<'g, V, K::Encoded, D, B>, Refused<V>> {
+        let coord = key_to_coord::<K::Encoded, D>(key, self.basis);
+        let mut node = Owned::new(Node::new(key, coord, val, seq));
+        let moving = is_move(val);
+        let mut replaced = false;
+
+        let inserted = loop {
+            let mut pred = Shared::null();
+            let mut curr = Shared::null();
+            let mut dp = 0;
+            let mut dc = 0;
+
+            // A failed attempt may have been misled by the hint; retry from
+            // the head.
+            let start = hint.take().and_then(|hint| Self::resume_point(&coord, hint, guard));
+            self.locate_pred(&coord, start, &mut pred, &mut curr, &mut dp, &mut dc, guard);
+
+            if dc == D {
+                node.reset_pending(guard);
+                let slot = match duplicates {
+                    Duplicates::Keep => self.push_duplicate(curr, node, guard),
+                    Duplicates::Reject | Duplicates::Replace => match self.store_value(curr, node, duplicates, guard) {
+                        Ok(replaced_now) => {
+                            replaced |= replaced_now;
+                            curr
+                        }
+                        Err(val) => {
+                            return Err((InsertError::DuplicateKey { key: key.to_u128() }, val));
+                        }
+                    },
+                };
+
+                // A purge that froze `curr` before the value was stored may
+                // have missed it; take it back and retry against the new head.
+                fence(Ordering::SeqCst);
+                if unsafe { curr.deref() }.purged.load(Ordering::Relaxed) {
+                    if let Some(taken) = unsafe { slot.deref() }.take_back(val) {
+                        val = taken;
+                        node = Owned::new(Node::new(key, coord, val, seq));
+                        continue;
                     }
                 }
-            } else {
-                break;
+                break (curr, slot);
             }
+
+            // `curr` may still be adopting children of its own; they have to
+            // be in place before the new node takes them over.
+            if let Some(curr) = unsafe { curr.as_ref() }.filter(|_| dp < dc) {
+                curr.finish_inserting(guard);
+            }
+
+            self.fill_new_node(&node, curr, dp, dc, guard);
+
+            let pred_node = unsafe { pred.deref() };
+            match pred_node.child[dp].compare_exchange(
+                curr,
+                node,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+                guard,
+            ) {
+                Ok(new_ptr) => {
+                    unsafe { new_ptr.deref() }.finish_inserting(guard);
+                    break (new_ptr, new_ptr);
+                }
+                Err(e) => node = e.new,
+            }
+        };
+
+        if !moving && !replaced {
+            self.len.add(1);
+        }
+        self.inserted.fetch_add(1, Ordering::SeqCst);
+        self.rewind_stack(key, guard);
+        Ok(inserted)
+    }
+
+    /// Where a search for `coord` can start instead of the head: `hint` and
+    /// the first dimension in which `coord` is larger, as long as `hint` is
+    /// still in the list and still links that dimension itself. Every node
+    /// the search would pass through before reaching `hint` shares its
+    /// coordinates up to that dimension.
+    fn resume_point<'g>(
+        coord: &[u32; D],
+        hint: Shared<'g, Node<V, K::Encoded, D, B>>,
+        guard: &'g Guard,
+    ) -> Option<Resume<'g, V, K::Encoded, D, B>> {
+        let hint_node = unsafe { hint.deref() };
+        if hint_node.purged.load(Ordering::Relaxed) {
+            return None;
         }
-    
-        let new_shared = Owned::new(new_stack).into_shared(guard);
-        match self.stack.compare_exchange(
-            old_shared,
-            new_shared,
-            Ordering::AcqRel,
-            Ordering::Acquire,
-            guard,
-        ) {
-            Ok(_) => break,
-            Err(e) => old_shared = e.current,
-        }
-        }
-    }
-}
-    
-    
-impl MDList {
-    pub fn delete_min<'g>(&self, stack: &Stack, guard: &'g Guard) -> Option<Shared<'g, Node>> {
-        let head = self.head.load(Ordering::Acquire, guard);
-        let head_node = unsafe { head.deref() };
-        
-
-    let mut curr = head_node.child[0].load(Ordering::Acquire, guard);
-        
-    while !curr.is_null() {
-        let node = unsafe { curr.deref() };
-        let val_ptr = node.val.load(Ordering::Acquire);
-        
-        if !val_ptr.is_null() {
-            let val_shared = unsafe { Shared::from(val_ptr as *const u8) };
-            
-            if !is_marked(val_shared, FDEL) {
-                let mark_ptr = set_mark_ptr(val_shared, FDEL);
-                if node.val.compare_exchange(
-                    val_ptr,
-                    mark_ptr.as_raw() as *mut u8,
-                    Ordering::AcqRel,
-                    Ordering::Acquire,
-                ).is_ok() {
-                    let next = node.child[0].load(Ordering::Acquire, guard);
-                    head_node.child[0].store(next, Ordering::Release);
-                    return Some(curr);
+        let d = (0..D).find(|&d| coord[d] != hint_node.coord[d]).unwrap_or(D);
+        if d < D
+            && (coord[d] < hint_node.coord[d]
+                || is_marked(hint_node.child[d].load(Ordering::Acquire, guard), FADP))
+        {
+            return None;
+        }
+        Some((hint, d))
+    }
+
+    /// Moves the value of `node` into `curr`, the node already holding its
+    /// key, which in a set keeps the only value for that key. Returns whether
+    /// a value was replaced, or the value itself if it was rejected. A move
+    /// never replaces anything.
+    fn store_value(
+        &self,
+        curr: Shared<'_, Node<V, K::Encoded, D, B>>,
+        node: Owned<Node<V, K::Encoded, D, B>>,
+        duplicates: Duplicates,
+        guard: &Guard,
+    ) -> Result<bool, *mut Slot<V>> {
+        let curr_node = unsafe { curr.deref() };
+        let val = node.val.swap(ptr::null_mut(), Ordering::Relaxed);
+        drop(node);
+
+        loop {
+            let old = curr_node.val.load(Ordering::SeqCst);
+            if is_move(old) {
+                unsafe { Move::<V, K::Encoded, D, B>::from_tag(old) }.help(curr_node);
+                continue;
+            }
+            if !old.is_null() && (duplicates != Duplicates::Replace || is_move(val)) {
+                return Err(val);
+            }
+            if curr_node
+                .val
+                .compare_exchange(old, val, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+            {
+                if old.is_null() {
+                    return Ok(false);
                 }
+                Slot::unref(old, guard);
+                return Ok(true);
             }
         }
-        
-        curr = node.child[0].load(Ordering::Acquire, guard);
     }
-        
-        None
+
+    fn push_duplicate<'g>(
+        &self,
+        curr: Shared<'g, Node<V, K::Encoded, D, B>>,
+        mut node: Owned<Node<V, K::Encoded, D, B>>,
+        guard: &'g Guard,
+    ) -> Shared<'g, Node<V, K::Encoded, D, B>> {
+        let curr_node = unsafe { curr.deref() };
+        loop {
+            let back = curr_node.back.load(Ordering::Acquire, guard);
+            let last = unsafe { back.as_ref() }.unwrap_or(curr_node);
+            let next = last.dup.load(Ordering::Acquire, guard);
+            if !next.is_null() {
+                // The back lags behind; help it on.
+                let _ = curr_node.back.compare_exchange(back, next, Ordering::AcqRel, Ordering::Acquire, guard);
+                continue;
+            }
+            match last.dup.compare_exchange(
+                Shared::null(),
+                node,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+                guard,
+            ) {
+                Ok(dup) => {
+                    let _ = curr_node.back.compare_exchange(back, dup, Ordering::AcqRel, Ordering::Acquire, guard);
+                    return dup;
+                }
+                Err(e) => node = e.new,
+            }
+        }
     }
 }
 
 
-impl MDList {
-    pub fn purge<'g>(
+impl<V, K: PriorityKey, const D: usize, const B: u32> MDList<V, K, D, B> {
+    #[allow(clippy::too_many_arguments)]
+    fn locate_pred<'g>(
         &self,
-        hd: Shared<'g, Node>,
-        prg: Shared<'g, Node>,
+        coord: &[u32; D],
+        start: Option<Resume<'g, V, K::Encoded, D, B>>,
+        pred: &mut Shared<'g, Node<V, K::Encoded, D, B>>,
+        curr: &mut Shared<'g, Node<V, K::Encoded, D, B>>,
+        dp: &mut usize,
+        dc: &mut usize,
         guard: &'g Guard,
     ) {
-        let current_head = self.head.load(Ordering::Acquire, guard);
-        if hd.as_raw() != current_head.as_raw() {
-            return;
+        *pred = Shared::null();
+        (*curr, *dc) = start.unwrap_or_else(|| (self.head.load(Ordering::Acquire, guard), 0));
+        *dp = 0;
+
+        while *dc < D {
+            while !curr.is_null() {
+                let curr_node = unsafe { curr.deref() };
+                if coord[*dc] <= curr_node.coord[*dc] {
+                    break;
+                }
+                *pred = *curr;
+                *dp = *dc;
+                curr_node.finish_inserting(guard);
+                let child = curr_node.child[*dc].load(Ordering::Acquire, guard);
+                *curr = clear_mark(child, FADP | FPRG);
+            }
+
+            if curr.is_null() || coord[*dc] < unsafe { curr.deref() }.coord[*dc] {
+                break;
+            }
+            *dc += 1;
         }
-    
-        let prg_ref = unsafe { prg.deref() };
-        let prgcopy = Owned::new(Node::clone_without_children(prg_ref));
-        let hdnew = Owned::new(unsafe { Node::new_fdel(current_head.deref().seq + 1) });
-    
-        let mut d = 0;
-        let mut pnt = hd;
-    
-        while d < DIMENSION {
-            if !self.locate_pivot(&mut pnt, prg, d, guard) {
-                pnt = hd;
-                d = 0;
-                continue;
+    }
+}
+
+
+impl<V, K: PriorityKey, const D: usize, const B: u32> MDList<V, K, D, B> {
+    fn fill_new_node<'g>(
+        &self,
+        node: &Node<V, K::Encoded, D, B>,
+        curr: Shared<'g, Node<V, K::Encoded, D, B>>,
+        dp: usize,
+        dc: usize,
+        guard: &'g Guard,
+    ) {
+        node.reset_pending(guard);
+
+        // Dimensions below `dp` are owned by the predecessor and can never
+        // hold children of this node.
+        for i in 0..dp {
+            node.child[i].store(set_mark_ptr(Shared::null(), FADP), Ordering::Relaxed);
+        }
+
+        for i in dp..D {
+            node.child[i].store(Shared::null(), Ordering::Relaxed);
+        }
+
+        if dc < D {
+            node.child[dc].store(curr, Ordering::Relaxed);
+        }
+
+        if dp < dc {
+            let desc = Desc {
+                curr: Atomic::from(curr),
+                dp: dp as u8,
+                dc: dc as u8,
+            };
+            node.pending.store(Owned::new(desc), Ordering::Relaxed);
+        }
+    }
+}
+
+
+impl<V, K: PriorityKey, const D: usize, const B: u32> MDList<V, K, D, B> {
+    /// Moves the shared deletion cursor back to its head if it has already
+    /// passed `key`, so that a node inserted behind it is visited again.
+    fn rewind_stack(&self, key: K::Encoded, guard: &Guard) {
+        let mut old_shared = self.stack.load(Ordering::SeqCst, guard);
+        loop {
+            let old = unsafe { old_shared.deref() };
+            let head = old.head.load(Ordering::Acquire, guard);
+            let last = old.del[D - 1].load(Ordering::Acquire, guard);
+            if last == head || key > unsafe { last.deref() }.key {
+                return;
             }
-    
-            let child = unsafe { pnt.deref() }.child[d].load(Ordering::Acquire, guard);
-    
-            if pnt.as_raw() == hd.as_raw() {
-                hdnew.child[d].store(child, Ordering::Release);
-                let marked_null = set_mark_ptr(Shared::null(), FDEL);
-                unsafe {
-                    prgcopy.deref().child[d].store(marked_null, Ordering::Release);
+
+            match self.stack.compare_exchange(
+                old_shared,
+                Owned::new(Stack::at(head)),
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+                guard,
+            ) {
+                Ok(_) => {
+                    unsafe { guard.defer_destroy(old_shared) };
+                    return;
                 }
-            } else {
-                unsafe {
-                    prgcopy.deref().child[d].store(child, Ordering::Release);
+                Err(e) => old_shared = e.current,
+            }
+        }
+    }
+}
+
+
+impl<V, K: PriorityKey, const D: usize, const B: u32> MDList<V, K, D, B> {
+    /// Deletes the entry with the smallest key. A stack left over from a
+    /// failed publication is taken from `spare` instead of allocating a new
+    /// one, and the one rejected this time is put back.
+    pub(crate) fn delete_min(
+        &self,
+        spare: &mut Option<Owned<Stack<V, K::Encoded, D, B>>>,
+        guard: &Guard,
+    ) -> Option<(K::Encoded, V)> {
+        let mut found = None;
+        self.delete_min_n(1, spare, guard, |entry| found = Some(entry));
+        found
+    }
+
+    /// Deletes the entry with the smallest key if `pred` accepts it.
+    fn delete_min_if(
+        &self,
+        pred: &mut Filter<'_, V, K::Encoded>,
+        guard: &Guard,
+    ) -> Option<(K::Encoded, V)> {
+        let mut found = None;
+        self.delete_min_n_where(1, &mut None, Some(pred), guard, |entry| found = Some(entry));
+        found
+    }
+
+    /// Deletes up to `n` entries with the smallest keys in one walk, passing
+    /// them to `sink` in key order. The deletion stack and the purge counter
+    /// are updated once for the whole batch. Returns the number deleted.
+    pub(crate) fn delete_min_n(
+        &self,
+        n: usize,
+        spare: &mut Option<Owned<Stack<V, K::Encoded, D, B>>>,
+        guard: &Guard,
+        sink: impl FnMut((K::Encoded, V)),
+    ) -> usize {
+        self.delete_min_n_where(n, spare, None, guard, sink)
+    }
+
+    /// Like `delete_min_n`, but when `pred` is given, stops at the first
+    /// entry it turns down and leaves that entry in place.
+    fn delete_min_n_where(
+        &self,
+        n: usize,
+        spare: &mut Option<Owned<Stack<V, K::Encoded, D, B>>>,
+        mut pred: Option<&mut Filter<'_, V, K::Encoded>>,
+        guard: &Guard,
+        mut sink: impl FnMut((K::Encoded, V)),
+    ) -> usize {
+        if n == 0 {
+            return 0;
+        }
+        let mut taken = 0;
+        let (seen, old_shared, head, cursor, moved) = 'retry: loop {
+            let seen = self.inserted.load(Ordering::SeqCst);
+            let old_shared = self.stack.load(Ordering::SeqCst, guard);
+            let old = unsafe { old_shared.deref() };
+            let head = old.head.load(Ordering::Acquire, guard);
+
+            let mut cursor = Cursor::load(old, guard);
+            let mut moved = false;
+            let mut curr = cursor.position();
+
+            loop {
+                // Equal keys share a node, so drain it before moving on.
+                let mut rejected = false;
+                while taken < n {
+                    match Self::take_from(curr, pred.as_deref_mut(), guard) {
+                        Claim::Taken(entry) => {
+                            sink(entry);
+                            taken += 1;
+                        }
+                        Claim::Empty => break,
+                        Claim::Rejected => {
+                            rejected = true;
+                            break;
+                        }
+                    }
+                }
+                if taken == n || rejected {
+                    break;
+                }
+                match cursor.advance(guard) {
+                    Advance::Node(next) => curr = next,
+                    Advance::End => break,
+                    Advance::Stale => {
+                        // Once a purge has replaced the head, the old one can
+                        // lead into nodes adopted since, so start over from
+                        // the current stack instead.
+                        if self.stack.load(Ordering::SeqCst, guard) != old_shared {
+                            continue 'retry;
+                        }
+                        cursor = Cursor::at(head);
+                        curr = head;
+                    }
                 }
+                moved = true;
+            }
+            break (seen, old_shared, head, cursor, moved);
+        };
+
+        if moved {
+            let new = match spare.take() {
+                Some(mut stack) => {
+                    *stack = cursor.to_stack(head);
+                    stack
+                }
+                None => Owned::new(cursor.to_stack(head)),
+            };
+            let published = match self.stack.compare_exchange(
+                old_shared,
+                new,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+                guard,
+            ) {
+                Ok(_) => {
+                    unsafe { guard.defer_destroy(old_shared) };
+                    true
+                }
+                Err(e) => {
+                    *spare = Some(e.new);
+                    false
+                }
+            };
+
+            // An insertion that raced with the walk may have landed behind
+            // the cursor just published without seeing it.
+            if published && self.inserted.load(Ordering::SeqCst) != seen {
+                self.rewind_stack(K::Encoded::MIN, guard);
+            }
+        }
+
+        if taken > 0 {
+            self.count_deletions(taken, None, guard);
+        }
+
+        taken
+    }
+
+    /// Accounts for `n` entries just deleted, and once enough deletions
+    /// have piled up since the last purge, purges the empty prefix up to
+    /// `bound` or the deletion cursor.
+    fn count_deletions(&self, n: usize, bound: Option<K::Encoded>, guard: &Guard) {
+        self.len.add(-(n as isize));
+        let marked = self.marked_node.fetch_add(n as u32, Ordering::Relaxed);
+        if marked + n as u32 >= self.r {
+            self.try_purge(bound, guard);
+        }
+    }
+
+    fn take_from(
+        node: Shared<'_, Node<V, K::Encoded, D, B>>,
+        pred: Option<&mut Filter<'_, V, K::Encoded>>,
+        guard: &Guard,
+    ) -> Claim<(K::Encoded, V)> {
+        let node = unsafe { node.deref() };
+        let key = node.key;
+        let claim = match pred {
+            Some(pred) => node.take_value_if(guard, &mut |val| pred(key, val)),
+            None => match node.take_value(guard) {
+                Some((_, val)) => Claim::Taken(val),
+                None => Claim::Empty,
+            },
+        };
+        match claim {
+            Claim::Taken(val) => Claim::Taken((key, val)),
+            Claim::Empty => Claim::Empty,
+            Claim::Rejected => Claim::Rejected,
+        }
+    }
+
+    /// Returns the smallest key still in the list without removing it.
+    pub(crate) fn min_key(&self, guard: &Guard) -> Option<K::Encoded> {
+        self.find_min(guard, |node| node.has_value(guard).then_some(node.key))
+    }
+
+    /// Walks the list from the deletion cursor and returns the first result
+    /// `f` produces for a node.
+    fn find_min<T>(
+        &self,
+        guard: &Guard,
+        f: impl Fn(&Node<V, K::Encoded, D, B>) -> Option<T>,
+    ) -> Option<T> {
+        'retry: loop {
+            let stack_shared = self.stack.load(Ordering::SeqCst, guard);
+            let stack = unsafe { stack_shared.deref() };
+            let head = stack.head.load(Ordering::Acquire, guard);
+
+            let mut cursor = Cursor::load(stack, guard);
+            let mut curr = cursor.position();
+            loop {
+                if let Some(found) = f(unsafe { curr.deref() }) {
+                    return Some(found);
+                }
+                match cursor.advance(guard) {
+                    Advance::Node(next) => curr = next,
+                    Advance::End => return None,
+                    Advance::Stale => {
+                        if self.stack.load(Ordering::SeqCst, guard) != stack_shared {
+                            continue 'retry;
+                        }
+                        cursor = Cursor::at(head);
+                        curr = head;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes the entry with the smallest key and returns it.
+    pub fn pop_min(&self) -> Option<(K, V)> {
+        let min = self.delete_min(&mut None, &epoch::pin());
+        min.map(|(key, val)| (K::decode(key), val))
+    }
+
+    /// Removes the entry with the largest key and returns it, oldest first
+    /// among equal keys. The maximum is found by descending from the head
+    /// through the highest coordinates first. Nodes emptied at the top end
+    /// are only purged once every node before them is empty too, so each
+    /// call walks past the ones earlier calls left behind; a list popped
+    /// mostly from the top is better served by [`MaxMDList`](crate::MaxMDList).
+    pub fn pop_max(&self) -> Option<(K, V)> {
+        let guard = &epoch::pin();
+        let (key, val) = self.floor_below(None, guard, |node| {
+            node.take_value(guard).map(|(_, val)| (node.key, val))
+        })?;
+        // The deletion cursor never moves past these nodes, so the purge
+        // they count towards is not bounded by it.
+        self.count_deletions(1, Some(K::Encoded::MAX), guard);
+        Some((K::decode(key), val))
+    }
+
+    /// Removes the entry with the smallest key only if `pred` accepts it.
+    /// `pred` looks at a borrow of the value, so no concurrent pop waits
+    /// for it, and the entry is claimed only if that value is still in place
+    /// afterwards: a concurrent pop cannot take the entry in between, and
+    /// when it wins the race, `pred` is asked again about the next smallest
+    /// entry instead. Among equal keys, only the oldest entry is offered.
+    pub fn pop_min_if(&self, mut pred: impl FnMut(K, &V) -> bool) -> Option<(K, V)>
+    where
+        V: Clone + Sync,
+    {
+        let mut pred = |key, offer: Offer<'_, V>| {
+            offer.borrow().is_some_and(|val| pred(K::decode(key), &val))
+        };
+        let min = self.delete_min_if(&mut pred, &epoch::pin());
+        min.map(|(key, val)| (K::decode(key), val))
+    }
+
+    /// Removes the entry with the smallest key if that key is at most
+    /// `threshold`.
+    pub fn pop_min_at_most(&self, threshold: K) -> Option<(K, V)> {
+        let threshold = threshold.encode();
+        let min = self.delete_min_if(&mut |key, _| key <= threshold, &epoch::pin());
+        min.map(|(key, val)| (K::decode(key), val))
+    }
+
+    /// Removes up to `k` entries with the smallest keys and appends them to
+    /// `out` in key order. Returns how many were removed, which is less than
+    /// `k` only if the list ran empty.
+    pub fn pop_min_n(&self, k: usize, out: &mut Vec<(K, V)>) -> usize {
+        out.reserve(k.min(self.len()));
+        self.delete_min_n(k, &mut None, &epoch::pin(), |(key, val)| {
+            out.push((K::decode(key), val))
+        })
+    }
+
+    /// Removes the oldest entry under `key`, wherever it is in the list, and
+    /// returns its value. The removal counts towards the next purge, which
+    /// cuts off the emptied node once every node before it is empty too.
+    pub fn remove(&self, key: K) -> Option<V> {
+        let key = key.encode();
+        self.check_key(key).ok()?;
+        let guard = &epoch::pin();
+        let val = self.lookup(key, guard, |node, guard| {
+            node.take_value(guard).map(|(_, val)| val)
+        })?;
+        // As in `pop_max`, the node may lie past the deletion cursor.
+        self.count_deletions(1, Some(K::Encoded::MAX), guard);
+        Some(val)
+    }
+
+    /// Removes every entry with a key below `cutoff` and returns them in key
+    /// order. The entries are claimed one at a time in a walk from the head,
+    /// so no other operation waits for the removal; entries inserted below
+    /// `cutoff` during the walk may be left in place. The emptied prefix is
+    /// purged afterwards, unless a purge is already running, in which case
+    /// a later one cuts it off.
+    pub fn remove_below(&self, cutoff: K) -> Vec<(K, V)> {
+        let removed = self.take_below(Some(cutoff.encode()), &epoch::pin());
+        removed.into_iter().map(|(key, val)| (K::decode(key), val)).collect()
+    }
+
+    /// Removes and drops every entry, as `remove_below` does for a cutoff
+    /// past the largest key.
+    pub fn clear(&self) {
+        drop(self.take_below(None, &epoch::pin()));
+    }
+
+    /// Whether any entry is stored under `key`.
+    pub fn contains(&self, key: K) -> bool {
+        let key = key.encode();
+        self.check_key(key).is_ok()
+            && self
+                .lookup(key, &epoch::pin(), |node, guard| node.has_value(guard).then_some(()))
+                .is_some()
+    }
+
+    /// Borrows the oldest value stored under `key`. As with
+    /// [`peek_min`](MDList::peek_min), the borrow does not keep the entry in
+    /// the list.
+    pub fn get(&self, key: K) -> Option<ValueRef<'_, V>>
+    where
+        V: Clone + Sync,
+    {
+        let key = key.encode();
+        self.check_key(key).ok()?;
+        self.lookup(key, &epoch::pin(), |node, guard| node.peek_value(guard))
+    }
+
+    /// Replaces the oldest value stored under `key` and returns the old one.
+    /// If the key is not in the list, `val` is handed back as the error.
+    pub fn update_value(&self, key: K, val: V) -> Result<V, V> {
+        let key = key.encode();
+        if self.check_key(key).is_err() {
+            return Err(val);
+        }
+        let new = Slot::boxed(val, 0);
+        let old = self.lookup(key, &epoch::pin(), |node, guard| loop {
+            let node = node.oldest(guard)?;
+            let old = node.load_val();
+            if old.is_null() {
+                // A pop claimed the value first; look again.
+                continue;
+            }
+            // The entry stays the same, so a handle to it stays valid.
+            unsafe { (*new).seq = (*old).seq };
+            if node
+                    .val
+                    .compare_exchange(old, new, Ordering::SeqCst, Ordering::Acquire)
+                    .is_ok()
+            {
+                return Slot::release(old, guard);
             }
-    
-            d += 1;
-        }
-    
-        let shared_prgcopy = prgcopy.into_shared(guard);
-    
-        let last_child = unsafe { shared_prgcopy.deref().child[DIMENSION - 1].load(Ordering::Acquire, guard) };
-        if is_marked(last_child, FDEL) {
-            hdnew.child[DIMENSION - 1].store(shared_prgcopy, Ordering::Release);
-        }
-    
-        let shared_hdnew = hdnew.into_shared(guard); 
-    
-        let desc1 = Box::new(Desc {
-            curr: Atomic::from(shared_hdnew),
-            dp: 0,
-            dc: 0,
         });
-        let desc_ptr1: *mut Desc = Box::into_raw(desc1);
-        let desc_shared1 = unsafe { Shared::from(desc_ptr1 as *const Desc) };
-        let marked_desc1 = set_mark_ptr(desc_shared1, FDEL);
-        unsafe { prg.deref() }.pending.store(marked_desc1, Ordering::Release);
-    
-        let desc2 = Box::new(Desc {
-            curr: Atomic::from(prg),
-            dp: 0,
-            dc: 0,
+        old.ok_or_else(|| Slot::into_inner(new))
+    }
+
+    /// Moves the entry behind `entry` to `new_key` and returns a handle to it
+    /// at its new place. The entry is linked under `new_key` first and then
+    /// switched over in one step, which any thread running into it helps to
+    /// complete, so a concurrent pop sees it at one priority or the other,
+    /// never both and never neither.
+    ///
+    /// Fails with [`InsertError::EntryNotFound`] if the entry has already
+    /// left the list, which includes being replaced by an insertion under
+    /// the same key, or if `entry` was handed out by another list. If
+    /// `new_key` is out of range or, in a list that keeps one entry per
+    /// key, already present, the entry stays where it was; a move never
+    /// replaces another entry.
+    pub fn change_priority(
+        &self,
+        entry: EntryHandle<K>,
+        new_key: K,
+    ) -> Result<EntryHandle<K>, InsertError> {
+        if entry.list != self.id {
+            return Err(InsertError::EntryNotFound);
+        }
+        let old_key = entry.key.encode();
+        let encoded = new_key.encode();
+        self.check_key(encoded)?;
+        let keyed = self.duplicates != Duplicates::Keep;
+        let guard = &epoch::pin();
+
+        loop {
+            let Some(holder) = self.locate_node(old_key, guard) else {
+                return Err(InsertError::EntryNotFound);
+            };
+            let holder = unsafe { holder.deref() };
+            // Even in a set the key alone does not name the entry: the node
+            // outlives its value, and takes the next one inserted.
+            let Some(from) = holder.chain(guard).find(|node| node.holds(entry.seq)) else {
+                return Err(InsertError::EntryNotFound);
+            };
+            if keyed && encoded == old_key {
+                return Ok(entry);
+            }
+
+            from.moving.fetch_add(1, Ordering::SeqCst);
+            // Pairs with the fence in `purge`: either the purge sees the move
+            // and leaves `holder` alone, or the move sees the purge and looks
+            // the entry up again once the purge is through with `holder`.
+            fence(Ordering::SeqCst);
+            let moved = if holder.purged.load(Ordering::Relaxed) {
+                None
+            } else {
+                self.move_value(from, entry.seq, encoded, keyed, guard)
+            };
+            from.moving.fetch_sub(1, Ordering::SeqCst);
+            if let Some(moved) = moved {
+                return moved.map(|()| EntryHandle { key: new_key, ..entry });
+            }
+        }
+    }
+
+    /// Moves the value stamped `seq` out of `from` to a node under `key`, or
+    /// returns `None` if the move was called off and has to start over.
+    fn move_value(
+        &self,
+        from: &Node<V, K::Encoded, D, B>,
+        seq: u32,
+        key: K::Encoded,
+        keyed: bool,
+        guard: &Guard,
+    ) -> Option<Result<(), InsertError>> {
+        let val = from.load_val();
+        if val.is_null() || unsafe { (*val).seq } != seq {
+            return Some(Err(InsertError::EntryNotFound));
+        }
+        let mv = Box::into_raw(Box::new(Move {
+            from,
+            to: AtomicPtr::new(ptr::null_mut()),
+            slot: val,
+            state: AtomicU8::new(PENDING),
+        }));
+        // Threads that ran into the descriptor may still be reading it.
+        let retire = || unsafe { guard.defer_unchecked(move || drop(Box::from_raw(mv))) };
+        let mv = unsafe { &*mv };
+
+        // The new node is stamped afresh, which puts the value last among
+        // equal keys; the value keeps its own stamp.
+        let duplicates = if keyed { Duplicates::Reject } else { Duplicates::Keep };
+        let to = match self.insert_stamped(key, self.next_seq(), mv.tag(), None, duplicates, guard) {
+            Ok((_, to)) => unsafe { to.deref() },
+            Err((e, _)) => {
+                mv.abort();
+                retire();
+                return Some(Err(e));
+            }
+        };
+        // Until now a thread that ran into `to` called the move off; from
+        // here on it helps to complete it. The insertion has been counted
+        // by `inserted` already, which tells a walk that saw neither node
+        // holding the value to look again.
+        mv.to.store(to as *const _ as *mut _, Ordering::SeqCst);
+        let state = mv.complete();
+        mv.finish(state);
+        if state == ABORTED {
+            mv.settle(to, ptr::null_mut());
+        }
+        retire();
+        if state == ABORTED {
+            return None;
+        }
+
+        // The value appeared behind the cursor if `key` is below it.
+        self.inserted.fetch_add(1, Ordering::SeqCst);
+        self.rewind_stack(key, guard);
+        Some(Ok(()))
+    }
+
+    /// Applies `f` to the node holding `key`. A purge only cuts off nodes
+    /// it found empty, so a node missing from the list holds nothing.
+    fn lookup<T>(
+        &self,
+        key: K::Encoded,
+        guard: &Guard,
+        f: impl Fn(&Node<V, K::Encoded, D, B>, &Guard) -> Option<T>,
+    ) -> Option<T> {
+        let node = self.locate_node(key, guard)?;
+        f(unsafe { node.deref() }, guard)
+    }
+
+    /// The node holding `key`, if the key has ever been inserted since the
+    /// last purge that covered it.
+    fn locate_node<'g>(
+        &self,
+        key: K::Encoded,
+        guard: &'g Guard,
+    ) -> Option<Shared<'g, Node<V, K::Encoded, D, B>>> {
+        let coord = key_to_coord::<K::Encoded, D>(key, self.basis);
+        let mut pred = Shared::null();
+        let mut curr = Shared::null();
+        let mut dp = 0;
+        let mut dc = 0;
+        self.locate_pred(&coord, None, &mut pred, &mut curr, &mut dp, &mut dc, guard);
+        (dc == D).then_some(curr)
+    }
+
+    /// Returns the number of entries in the list. Concurrent insertions and
+    /// removals may or may not be counted, so the result is only an
+    /// estimate while the list is being modified.
+    pub fn len(&self) -> usize {
+        self.len.sum().max(0) as usize
+    }
+
+    /// Whether the list holds no entries. Unlike `len`, the answer is exact:
+    /// the list was empty at some point during the call. Entries past the
+    /// deletion cursor are found without going back to the head; only a list
+    /// that looks empty from there is walked in full.
+    ///
+    /// A walk that finds nothing only counts if no insertion happened while
+    /// it ran, since the entry inserted may be behind it. The call is not
+    /// wait-free: as long as insertions keep landing during each walk of a
+    /// list whose entries are popped as fast as they arrive, it walks again.
+    pub fn is_empty(&self) -> bool {
+        let guard = &epoch::pin();
+        loop {
+            let seen = self.inserted.load(Ordering::SeqCst);
+            if self.min_key(guard).is_some() || self.any_value(guard) {
+                return false;
+            }
+            // Every value inserted before `seen` was visited, so nothing was
+            // in the list once the walk ended unless an insertion, or a move
+            // linking its new node, got in the way.
+            if self.inserted.load(Ordering::SeqCst) == seen {
+                return true;
+            }
+        }
+    }
+
+    /// Returns the smallest key above `key` that has an entry.
+    pub fn successor(&self, key: K) -> Option<K> {
+        let lo = next_key(key.encode())?;
+        let guard = &epoch::pin();
+        self.ceiling(lo, guard, |node| node.has_value(guard).then_some(node.key))
+            .map(K::decode)
+    }
+
+    /// Returns the largest key below `key` that has an entry.
+    pub fn predecessor(&self, key: K) -> Option<K> {
+        let guard = &epoch::pin();
+        self.floor_below(Some(key.encode()), guard, |node| {
+            node.has_value(guard).then_some(node.key)
+        })
+        .map(K::decode)
+    }
+
+    /// Iterates over the entries with keys in `range`, in key order and
+    /// oldest first among equal keys. Each step looks up the next key again,
+    /// so entries inserted or removed while iterating may or may not be
+    /// seen, but none is yielded twice.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Range<'_, V, K, D, B>
+    where
+        V: Clone + Sync,
+    {
+        Range::new(self, range)
+    }
+
+    /// Removes every entry `pred` turns down, while other threads keep
+    /// inserting and popping. `pred` looks at a borrow of each value, and
+    /// the entry is removed only if that value is still in place, so an
+    /// entry popped or updated concurrently is never removed in its stead.
+    /// `pred` may be asked about an entry more than once, and entries
+    /// inserted during the walk may or may not be visited.
+    pub fn retain(&self, mut pred: impl FnMut(K, &V) -> bool)
+    where
+        V: Clone + Sync,
+    {
+        self.drain_filter(|key, val| !pred(key, val)).for_each(drop);
+    }
+
+    /// Returns an iterator that removes the entries `pred` accepts as it
+    /// walks the list in key order, and yields them. Each step claims one
+    /// entry, so the entries it has not yielded when it is dropped stay in
+    /// the list.
+    /// The same caveats as for [`retain`](MDList::retain) apply.
+    pub fn drain_filter<F>(&self, pred: F) -> DrainFilter<'_, V, K, F, D, B>
+    where
+        V: Clone + Sync,
+        F: FnMut(K, &V) -> bool,
+    {
+        DrainFilter::new(self, pred)
+    }
+
+    /// Walks the list in key order from the first node with a key of at
+    /// least `lo`, and returns the first result `f` produces for a node.
+    ///
+    /// The starting point is found the way `locate_pred` searches for `lo`,
+    /// recording each node passed in a cursor so that the walk can go on
+    /// from there.
+    fn ceiling<T>(
+        &self,
+        lo: K::Encoded,
+        guard: &Guard,
+        mut f: impl FnMut(&Node<V, K::Encoded, D, B>) -> Option<T>,
+    ) -> Option<T> {
+        // Keys past the coordinates would wrap around to small ones.
+        let capacity = (self.basis as u128).checked_pow(D as u32);
+        if capacity.is_some_and(|capacity| lo.to_u128() >= capacity) {
+            return None;
+        }
+        let coord = key_to_coord::<K::Encoded, D>(lo, self.basis);
+        let leaf = Shared::from(&*self.leaf as *const _);
+        'retry: loop {
+            let head = self.head.load(Ordering::Acquire, guard);
+            let mut cursor = Cursor::at(head);
+            let mut curr = head;
+            let mut start = Some(head);
+            'descend: for (d, &c) in coord.iter().enumerate() {
+                while c > unsafe { curr.deref() }.coord[d] {
+                    let curr_node = unsafe { curr.deref() };
+                    curr_node.finish_inserting(guard);
+                    let next = curr_node.child[d].load(Ordering::Acquire, guard);
+                    if is_marked(next, FADP) {
+                        continue 'retry;
+                    }
+                    let next = clear_mark(next, FPRG);
+                    if next.is_null() {
+                        // Everything `curr` leads to in higher dimensions
+                        // shares its smaller coordinate, so skip it.
+                        cursor.del[d + 1..].fill(leaf);
+                        start = None;
+                        break 'descend;
+                    }
+                    cursor.del[d..].fill(next);
+                    curr = next;
+                    start = Some(next);
+                }
+                if c < unsafe { curr.deref() }.coord[d] {
+                    break;
+                }
+            }
+
+            let mut next = start;
+            loop {
+                if let Some(node) = next {
+                    if let Some(found) = f(unsafe { node.deref() }) {
+                        return Some(found);
+                    }
+                }
+                next = match cursor.advance(guard) {
+                    Advance::Node(node) => Some(node),
+                    Advance::End => return None,
+                    Advance::Stale => continue 'retry,
+                };
+            }
+        }
+    }
+
+    /// Takes the oldest value `pred` accepts out of the first node at or
+    /// after `lo` that has one, and returns it with its key and stamp. Under
+    /// `lo` itself, values stamped `after` or earlier are skipped.
+    pub(crate) fn take_entry_from(
+        &self,
+        lo: K::Encoded,
+        after: Option<u32>,
+        pred: &mut Filter<'_, V, K::Encoded>,
+        guard: &Guard,
+    ) -> Option<(K::Encoded, u32, V)> {
+        let found = self.ceiling(lo, guard, |node| {
+            let key = node.key;
+            let after = if key == lo { after } else { None };
+            let (seq, val) = node.take_first_if(after, guard, &mut |offer| pred(key, offer))?;
+            Some((key, seq, val))
         });
-        let desc_ptr2: *mut Desc = Box::into_raw(desc2);
-        let desc_shared2 = unsafe { Shared::from(desc_ptr2 as *const Desc) };
-        let marked_desc2 = set_mark_ptr(desc_shared2, FDEL);
-        unsafe { hd.deref() }.pending.store(marked_desc2, Ordering::Release);
-    
-        self.head.store(shared_hdnew, Ordering::Release);
-    }
-    
-    
-    
-    
-pub fn locate_pivot<'g>(
-    &self,
-    pnt: &mut Shared<'g, Node>,
-    prg: Shared<'g, Node>,
-    d: usize,
-    guard: &'g Guard,
-) -> bool {
-    while {
-        let prg_ref: &Node = unsafe { prg.deref() };       
-        let pnt_ref: &Node = unsafe { pnt.as_ref().unwrap() };       
-        prg_ref.coord[d] > pnt_ref.coord[d]
-    } {
-        self.finish_inserting(*pnt, d, d, guard);
-
-        let pnt_node: &Node = unsafe { pnt.as_ref().unwrap() };       
-        let child = pnt_node.child[d].load(Ordering::Acquire, guard);
-        *pnt = clear_mark(child, FADP | FPRG);
-    }
-
-    loop {
-        let pnt_node: &Node = unsafe { pnt.as_ref().unwrap() };
-
-        let child = pnt_node.child[d].load(Ordering::Acquire, guard);
-        let marked = set_mark_ptr(child, FPRG);
-
-        if pnt_node.child[d]
-            .compare_exchange(child, marked, Ordering::AcqRel, Ordering::Acquire, guard)
-            .is_ok()
+        if found.is_some() {
+            self.count_deletions(1, Some(K::Encoded::MAX), guard);
+        }
+        found
+    }
+
+    /// Returns the first key of at least `lo` that has entries, with copies
+    /// of its values.
+    pub(crate) fn entries_from(&self, lo: K::Encoded, guard: &Guard) -> Option<(K::Encoded, Vec<V>)>
+    where
+        V: Clone + Sync,
+    {
+        self.ceiling(lo, guard, |node| {
+            let values = node.clone_values(guard);
+            (!values.is_empty()).then_some((node.key, values))
+        })
+    }
+
+    /// Walks the nodes below `hi`, or all of them, from the largest key down
+    /// and returns the first result `f` produces. Subtrees are skipped whole
+    /// when their smallest key, the one of their root, is not below `hi`.
+    fn floor_below<T>(
+        &self,
+        hi: Option<K::Encoded>,
+        guard: &Guard,
+        f: impl Fn(&Node<V, K::Encoded, D, B>) -> Option<T>,
+    ) -> Option<T> {
+        enum Visit<'g, V, K, const D: usize, const B: u32> {
+            Node(&'g Node<V, K, D, B>),
+            Subtree(Shared<'g, Node<V, K, D, B>>, usize),
+        }
+
+        'retry: loop {
+            let mut pending = vec![Visit::Subtree(self.head.load(Ordering::Acquire, guard), 0)];
+            while let Some(visit) = pending.pop() {
+                match visit {
+                    Visit::Node(node) => {
+                        if let Some(found) = f(node) {
+                            return Some(found);
+                        }
+                    }
+                    Visit::Subtree(root, dim) => {
+                        let node = match unsafe { root.as_ref() } {
+                            Some(node) if hi.is_none_or(|hi| node.key < hi) => node,
+                            _ => continue,
+                        };
+                        // Popped in reverse: the subtrees from the lowest
+                        // dimension up, then the node itself.
+                        pending.push(Visit::Node(node));
+                        node.finish_inserting(guard);
+                        for d in (dim..D).rev() {
+                            let child = node.child[d].load(Ordering::Acquire, guard);
+                            if is_marked(child, FADP) {
+                                continue 'retry;
+                            }
+                            pending.push(Visit::Subtree(clear_mark(child, FPRG), d));
+                        }
+                    }
+                }
+            }
+            return None;
+        }
+    }
+
+    /// Whether any node reachable from the head holds a value. Unlike
+    /// `min_key` this does not start at the deletion cursor, which may have
+    /// passed values inserted behind it.
+    fn any_value(&self, guard: &Guard) -> bool {
+        'retry: loop {
+            let head = self.head.load(Ordering::Acquire, guard);
+            let mut cursor = Cursor::at(head);
+            let mut curr = head;
+            loop {
+                if unsafe { curr.deref() }.has_value(guard) {
+                    return true;
+                }
+                match cursor.advance(guard) {
+                    Advance::Node(next) => curr = next,
+                    Advance::End => return false,
+                    Advance::Stale => continue 'retry,
+                }
+            }
+        }
+    }
+
+    /// Returns the smallest key in the list without removing its entry.
+    pub fn peek_min_key(&self) -> Option<K> {
+        self.min_key(&epoch::pin()).map(K::decode)
+    }
+
+    /// Returns the entry `pop_min` would remove, with its value borrowed.
+    /// A concurrent `pop_min` may still claim the entry; it then takes a
+    /// clone of the value instead of waiting for the borrow to end.
+    pub fn peek_min(&self) -> Option<(K, ValueRef<'_, V>)>
+    where
+        V: Clone + Sync,
+    {
+        let guard = &epoch::pin();
+        self.find_min(guard, |node| {
+            node.peek_value(guard).map(|val| (K::decode(node.key), val))
+        })
+    }
+}
+
+
+impl<V, K: PriorityKey, const D: usize, const B: u32> MDList<V, K, D, B> {
+    /// Purges the empty prefix of the list up to `bound`, or up to the
+    /// deletion cursor, unless another purge is already running.
+    fn try_purge(&self, bound: Option<K::Encoded>, guard: &Guard) {
+        if self
+            .purging
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::Relaxed)
+            .is_err()
         {
-            break;
-        } else if is_marked(child, FADP | FPRG) {
-            break;
+            return;
+        }
+
+        self.marked_node.store(0, Ordering::Relaxed);
+        let stack = unsafe { self.stack.load(Ordering::SeqCst, guard).deref() };
+        let hd = stack.head.load(Ordering::Acquire, guard);
+        let bound = bound.or_else(|| {
+            let prg = stack.del[D - 1].load(Ordering::Acquire, guard);
+            (prg != hd).then(|| unsafe { prg.deref() }.key)
+        });
+        if let Some(bound) = bound {
+            self.purge(hd, bound, guard);
+        }
+        self.purging.store(false, Ordering::SeqCst);
+    }
+
+    /// Takes every entry with a key below `hi`, or every entry at all, out of
+    /// the list in one walk from the head, then purges the prefix they leave
+    /// empty. Returns the entries in key order, equal keys oldest first.
+    fn take_below(&self, hi: Option<K::Encoded>, guard: &Guard) -> Vec<(K::Encoded, V)> {
+        if hi == Some(K::Encoded::MIN) {
+            return Vec::new();
+        }
+        let mut removed = Vec::new();
+        self.ceiling(K::Encoded::MIN, guard, |node| {
+            if hi.is_some_and(|hi| node.key >= hi) {
+                return Some(());
+            }
+            while let Some((_, val)) = node.take_value(guard) {
+                removed.push((node.key, val));
+            }
+            None
+        });
+        self.len.add(-(removed.len() as isize));
+
+        let bound = hi.map_or(K::Encoded::MAX, |hi| K::Encoded::from_u128(hi.to_u128() - 1));
+        self.try_purge(Some(bound), guard);
+        removed
+    }
+
+    /// Cuts the nodes from `hd` up to the last one before the first node
+    /// that still holds a value, and none with a key past `bound`, off the
+    /// list by publishing a new head. Values never leave the list for this:
+    /// a node is only cut once it has been frozen empty.
+    fn purge<'g>(
+        &self,
+        hd: Shared<'g, Node<V, K::Encoded, D, B>>,
+        bound: K::Encoded,
+        guard: &'g Guard,
+    ) {
+        // Freeze the prefix in key order so that no insertion can link into
+        // it any more. A node's links are frozen only once it is known to be
+        // empty; the first node found holding a value ends the prefix.
+        let mut prefix = Vec::new();
+        let mut pending = vec![(hd, 0)];
+        while let Some((curr, dim)) = pending.pop() {
+            let node = unsafe { curr.deref() };
+            if node.key > bound {
+                break;
+            }
+            node.finish_inserting(guard);
+            node.purged.store(true, Ordering::Relaxed);
+            // Pairs with the fence in `insert_stamped`: a value stored on the
+            // node is either seen here or taken back by its inserter.
+            fence(Ordering::SeqCst);
+            if !node.is_clear(guard) {
+                node.purged.store(false, Ordering::Relaxed);
+                break;
+            }
+            // Pushed from the lowest dimension up, so that the subtree of the
+            // highest one, which holds the smallest keys, is visited next.
+            for d in dim..D {
+                let child = node.child[d].fetch_or(FPRG, Ordering::AcqRel, guard);
+                // Adopted children are reached through their adopter.
+                if is_marked(child, FADP) {
+                    continue;
+                }
+                let child = clear_mark(child, FPRG);
+                if !child.is_null() {
+                    pending.push((child, d));
+                }
+            }
+            prefix.push(curr);
+        }
+
+        let Some(&prg) = prefix.last() else {
+            return;
+        };
+        if prg == hd {
+            // Cutting the head alone gains nothing; thaw it instead.
+            let hd_node = unsafe { hd.deref() };
+            for child in &hd_node.child {
+                child.fetch_and(!FPRG, Ordering::AcqRel, guard);
+            }
+            hd_node.purged.store(false, Ordering::Relaxed);
+            return;
+        }
+        let prg_node = unsafe { prg.deref() };
+
+        let mut pnt = hd;
+        let mut pivots = [hd; D];
+        for (d, pivot) in pivots.iter_mut().enumerate() {
+            self.locate_pivot(&mut pnt, prg_node, d, guard);
+            *pivot = pnt;
+        }
+
+        // The new head keeps the subtrees of the old head that follow `prg`
+        // in the dimensions where `prg` has a zero coordinate; everything
+        // else hangs off a copy of `prg` in the first dimension where it
+        // leaves the old head.
+        let split = pivots.iter().position(|&p| p != hd).unwrap_or(D);
+        let hdnew = Owned::new(Node::new_fdel(self.next_seq()));
+        let prgcopy = Owned::new(prg_node.clone_without_children());
+
+        let hd_node = unsafe { hd.deref() };
+        for d in 0..split {
+            let child = hd_node.child[d].load(Ordering::Acquire, guard);
+            hdnew.child[d].store(clear_mark(child, FADP | FPRG), Ordering::Relaxed);
+            prgcopy.child[d].store(set_mark_ptr(Shared::null(), FADP), Ordering::Relaxed);
+        }
+        for (d, pivot) in pivots.iter().enumerate().skip(split) {
+            let child = unsafe { pivot.deref() }.child[d].load(Ordering::Acquire, guard);
+            prgcopy.child[d].store(clear_mark(child, FADP | FPRG), Ordering::Relaxed);
+        }
+        if split < D {
+            hdnew.child[split].store(prgcopy, Ordering::Relaxed);
+        }
+
+        let hdnew = hdnew.into_shared(guard);
+        self.head.store(hdnew, Ordering::Release);
+        let old_stack = self.stack.swap(Owned::new(Stack::at(hdnew)), Ordering::SeqCst, guard);
+
+        // Nothing reachable from the new head leads back into the prefix,
+        // so only threads pinned before the swap can still see it.
+        unsafe {
+            guard.defer_destroy(old_stack);
+            for curr in prefix {
+                guard.defer_destroy(curr);
+            }
         }
     }
 
-    let pnt_node: &Node = unsafe { pnt.as_ref().unwrap() };
-    let child = pnt_node.child[d].load(Ordering::Acquire, guard);
-    if is_marked(child, FPRG) {
-        *pnt = clear_mark(child, FPRG);
-        return true;
+    /// Walks `pnt` along dimension `d` to the node that shares the first
+    /// `d + 1` coordinates with `prg`.
+    fn locate_pivot<'g>(
+        &self,
+        pnt: &mut Shared<'g, Node<V, K::Encoded, D, B>>,
+        prg: &Node<V, K::Encoded, D, B>,
+        d: usize,
+        guard: &'g Guard,
+    ) {
+        loop {
+            let pnt_node = unsafe { pnt.deref() };
+            if pnt_node.coord[d] >= prg.coord[d] {
+                return;
+            }
+            let child = pnt_node.child[d].load(Ordering::Acquire, guard);
+            *pnt = clear_mark(child, FADP | FPRG);
+        }
+    }
+}
+
+
+/// Collects into a list built from [`Config::default`]. Panics if that
+/// configuration does not fit the key type.
+impl<V, K: PriorityKey, const D: usize, const B: u32> FromIterator<(K, V)> for MDList<V, K, D, B> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut mdlist = match Self::with_config(Config::default()) {
+            Ok(mdlist) => mdlist,
+            Err(e) => panic!("invalid MDList configuration: {}", e),
+        };
+        mdlist.extend(iter);
+        mdlist
+    }
+}
+
+/// Panics if a key is rejected; use [`MDList::insert_many`] to handle that.
+impl<V, K: PriorityKey, const D: usize, const B: u32> Extend<(K, V)> for MDList<V, K, D, B> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        if let Err(e) = self.insert_many(iter) {
+            panic!("failed to extend MDList: {}", e);
+        }
     }
+}
 
-    false
+impl<V, K: PriorityKey, const D: usize, const B: u32> Drop for MDList<V, K, D, B> {
+    fn drop(&mut self) {
+        let guard = unsafe { epoch::unprotected() };
+        drop(unsafe { self.stack.load(Ordering::Relaxed, guard).into_owned() });
+
+        let mut pending = vec![(self.head.load(Ordering::Relaxed, guard), 0)];
+        while let Some((curr, dim)) = pending.pop() {
+            let node = unsafe { curr.into_owned() };
+            for d in dim..D {
+                let child = node.child[d].load(Ordering::Relaxed, guard);
+                if !child.is_null() && !is_marked(child, FADP) {
+                    pending.push((clear_mark(child, FPRG), d));
+                }
+            }
+        }
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::{seq_before, MDList};
+    use crate::Config;
+    use crossbeam::epoch;
+    use std::sync::atomic::Ordering;
+
+    #[test]
+    fn seq_order_survives_wraparound() {
+        assert!(seq_before(1, 2));
+        assert!(!seq_before(2, 1));
+        assert!(!seq_before(5, 5));
+        assert!(seq_before(u32::MAX, 0));
+        assert!(seq_before(u32::MAX - 10, 10));
+    }
+
+    #[test]
+    fn removes_purge_the_emptied_prefix() {
+        let config = Config {
+            purge_threshold: 4,
+            ..Config::new(1 << 16)
+        };
+        let pq = MDList::<_>::with_config(config).unwrap();
+        let guard = &epoch::pin();
+        let head = pq.head.load(Ordering::Acquire, guard);
+        for key in 0..16 {
+            pq.insert(key, key).unwrap();
+            assert_eq!(pq.remove(key), Some(key));
+        }
+        assert_ne!(pq.head.load(Ordering::Acquire, guard), head);
+        assert!(pq.is_empty());
+    }
 
-}
\ No newline at end of file
+    #[test]
+    fn duplicate_chains_shed_emptied_nodes() {
+        let pq = MDList::new(16);
+        pq.insert(3, 0).unwrap();
+        for i in 1..1000 {
+            pq.insert(3, i).unwrap();
+            assert_eq!(pq.pop_min(), Some((3, i - 1)));
+        }
+        let guard = &epoch::pin();
+        let holder = unsafe { pq.locate_node(3, guard).unwrap().deref() };
+        assert!(holder.chain(guard).count() <= 2);
+    }
+}
@@ -0,0 +1,124 @@
+use crossbeam::epoch;
+use std::ops::{Bound, RangeBounds};
+
+use crate::key::{Key, PriorityKey};
+use crate::mdlist::{next_key, MDList, Offer};
+
+/// Iterator over the entries of an [`MDList`] within a key range, created
+/// by [`MDList::range`]. Values are cloned, since the entries stay in the
+/// list and may be popped at any time.
+pub struct Range<'a, V, K: PriorityKey, const D: usize, const B: u32> {
+    list: &'a MDList<V, K, D, B>,
+    /// Smallest key not looked at yet; `None` once the range is exhausted.
+    next: Option<K::Encoded>,
+    end: Bound<K::Encoded>,
+    key: Option<K>,
+    values: std::vec::IntoIter<V>,
+}
+
+impl<'a, V: Clone + Sync, K: PriorityKey, const D: usize, const B: u32> Range<'a, V, K, D, B> {
+    pub(crate) fn new<R: RangeBounds<K>>(list: &'a MDList<V, K, D, B>, range: R) -> Self {
+        let next = match range.start_bound() {
+            Bound::Included(key) => Some(key.encode()),
+            Bound::Excluded(key) => next_key(key.encode()),
+            Bound::Unbounded => Some(K::Encoded::MIN),
+        };
+        Range {
+            list,
+            next,
+            end: range.end_bound().map(|key| key.encode()),
+            key: None,
+            values: Vec::new().into_iter(),
+        }
+    }
+}
+
+impl<V: Clone + Sync, K: PriorityKey, const D: usize, const B: u32> Iterator for Range<'_, V, K, D, B> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        loop {
+            if let Some(val) = self.values.next() {
+                return self.key.map(|key| (key, val));
+            }
+
+            let lo = self.next?;
+            let found = self.list.entries_from(lo, &epoch::pin());
+            let (key, values) = match found {
+                Some((key, values)) if self.end_after(key) => (key, values),
+                _ => {
+                    self.next = None;
+                    return None;
+                }
+            };
+            self.next = next_key(key);
+            self.key = Some(K::decode(key));
+            self.values = values.into_iter();
+        }
+    }
+}
+
+impl<V, K: PriorityKey, const D: usize, const B: u32> Range<'_, V, K, D, B> {
+    fn end_after(&self, key: K::Encoded) -> bool {
+        match self.end {
+            Bound::Included(end) => key <= end,
+            Bound::Excluded(end) => key < end,
+            Bound::Unbounded => true,
+        }
+    }
+}
+
+/// Iterator that removes and yields the entries of an [`MDList`] accepted
+/// by a predicate, created by [`MDList::drain_filter`]. Each step claims a
+/// single entry, so dropping it leaves every entry it has not yielded.
+pub struct DrainFilter<'a, V, K: PriorityKey, F, const D: usize, const B: u32> {
+    list: &'a MDList<V, K, D, B>,
+    pred: F,
+    /// Smallest key not looked at yet; `None` once the list is exhausted.
+    next: Option<K::Encoded>,
+    /// Stamp of the last entry looked at under `next`, whose values up to
+    /// it have been offered to `pred` already.
+    after: Option<u32>,
+}
+
+impl<'a, V, K, F, const D: usize, const B: u32> DrainFilter<'a, V, K, F, D, B>
+where
+    K: PriorityKey,
+    V: Clone + Sync,
+    F: FnMut(K, &V) -> bool,
+{
+    pub(crate) fn new(list: &'a MDList<V, K, D, B>, pred: F) -> Self {
+        DrainFilter {
+            list,
+            pred,
+            next: Some(K::Encoded::MIN),
+            after: None,
+        }
+    }
+}
+
+impl<V, K, F, const D: usize, const B: u32> Iterator for DrainFilter<'_, V, K, F, D, B>
+where
+    K: PriorityKey,
+    V: Clone + Sync,
+    F: FnMut(K, &V) -> bool,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        let lo = self.next?;
+        let pred = &mut self.pred;
+        let mut pred = |key, offer: Offer<'_, V>| {
+            offer.borrow().is_some_and(|val| pred(K::decode(key), &val))
+        };
+        let taken = self.list.take_entry_from(lo, self.after, &mut pred, &epoch::pin());
+        let Some((key, seq, val)) = taken else {
+            self.next = None;
+            return None;
+        };
+        // Later entries under the same key come next.
+        self.next = Some(key);
+        self.after = Some(seq);
+        Some((K::decode(key), val))
+    }
+}
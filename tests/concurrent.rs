@@ -0,0 +1,746 @@
+use lockprio::{Config, Duplicates, MDList, MaxMDList};
+use loom::sync::Arc;
+use loom::thread;
+
+#[test]
+fn basic_concurrent() {
+    loom::model(|| {
+        let pq = Arc::new(MDList::new(10000));
+
+        let pq1 = pq.clone();
+        let t1 = thread::spawn(move || {
+            pq1.insert(1, 1u8).unwrap();
+        });
+
+        let pq2 = pq.clone();
+        let t2 = thread::spawn(move || {
+            pq2.pop_min();
+        });
+
+        t1.join().unwrap();
+        t2.join().unwrap();
+    });
+}
+
+#[test]
+fn concurrent_inserts_keep_every_key() {
+    let pq = std::sync::Arc::new(MDList::new(10000));
+    let threads: Vec<_> = (0..4u32)
+        .map(|t| {
+            let pq = pq.clone();
+            std::thread::spawn(move || {
+                for i in 0..500 {
+                    pq.insert(i * 4 + t, i * 4 + t).unwrap();
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    for key in 0..2000 {
+        assert_eq!(pq.pop_min(), Some((key, key)));
+    }
+    assert_eq!(pq.pop_min(), None);
+}
+
+#[test]
+fn concurrent_pops_return_every_key() {
+    let pq = std::sync::Arc::new(MDList::new(10000));
+    let producers: Vec<_> = (0..4u32)
+        .map(|t| {
+            let pq = pq.clone();
+            std::thread::spawn(move || {
+                for i in 0..2000 {
+                    pq.insert(i * 4 + t, i * 4 + t).unwrap();
+                }
+            })
+        })
+        .collect();
+    let consumers: Vec<_> = (0..4)
+        .map(|_| {
+            let pq = pq.clone();
+            std::thread::spawn(move || {
+                let mut popped = Vec::new();
+                for _ in 0..1500 {
+                    popped.extend(pq.pop_min());
+                }
+                popped
+            })
+        })
+        .collect();
+    for t in producers {
+        t.join().unwrap();
+    }
+
+    let mut popped: Vec<_> = consumers
+        .into_iter()
+        .flat_map(|t| t.join().unwrap())
+        .collect();
+    assert_eq!(pq.len(), 8000 - popped.len());
+    popped.extend(std::iter::from_fn(|| pq.pop_min()));
+    popped.sort_unstable();
+    assert_eq!(popped, (0..8000).map(|key| (key, key)).collect::<Vec<_>>());
+    assert!(pq.is_empty());
+}
+
+#[test]
+fn is_empty_races_with_purges() {
+    let config = Config {
+        purge_threshold: 1,
+        ..Config::new(10000)
+    };
+    let pq = std::sync::Arc::new(MDList::<_>::with_config(config).unwrap());
+    pq.insert(9999, 9999).unwrap();
+    let churners: Vec<_> = (0..3)
+        .map(|t| {
+            let pq = pq.clone();
+            std::thread::spawn(move || {
+                for i in 0..3000 {
+                    pq.insert((i * 3 + t) % 9000, i).unwrap();
+                    pq.pop_min_at_most(9998);
+                }
+            })
+        })
+        .collect();
+    while churners.iter().any(|t| !t.is_finished()) {
+        assert!(!pq.is_empty());
+    }
+    for t in churners {
+        t.join().unwrap();
+    }
+    while pq.pop_min_at_most(9998).is_some() {}
+    assert_eq!(pq.pop_min(), Some((9999, 9999)));
+    assert!(pq.is_empty());
+}
+
+#[test]
+fn batch_pops_return_every_key() {
+    let pq = std::sync::Arc::new(MDList::new(10000));
+    let producers: Vec<_> = (0..4u32)
+        .map(|t| {
+            let pq = pq.clone();
+            std::thread::spawn(move || {
+                for i in 0..2000 {
+                    pq.insert(i * 4 + t, ()).unwrap();
+                }
+            })
+        })
+        .collect();
+    let consumers: Vec<_> = (0..4)
+        .map(|_| {
+            let pq = pq.clone();
+            std::thread::spawn(move || {
+                let mut popped = Vec::new();
+                for _ in 0..300 {
+                    pq.pop_min_n(5, &mut popped);
+                }
+                popped
+            })
+        })
+        .collect();
+    for t in producers {
+        t.join().unwrap();
+    }
+
+    let mut popped: Vec<_> = consumers
+        .into_iter()
+        .flat_map(|t| t.join().unwrap())
+        .collect();
+    while pq.pop_min_n(64, &mut popped) > 0 {}
+    popped.sort_unstable();
+    assert_eq!(popped, (0..8000).map(|key| (key, ())).collect::<Vec<_>>());
+}
+
+#[test]
+fn purges_keep_concurrent_pops_in_order() {
+    let config = Config {
+        purge_threshold: 1,
+        ..Config::new(10000)
+    };
+    let pq = std::sync::Arc::new(MDList::<_>::with_config(config).unwrap());
+    for i in 0..8000u32 {
+        pq.insert(i / 64, i).unwrap();
+    }
+    let consumers: Vec<_> = (0..4)
+        .map(|_| {
+            let pq = pq.clone();
+            std::thread::spawn(move || {
+                let mut popped = Vec::new();
+                while let Some((key, val)) = pq.pop_min() {
+                    assert_eq!(key, val / 64);
+                    popped.push(val);
+                }
+                popped
+            })
+        })
+        .collect();
+
+    let mut popped = Vec::new();
+    for t in consumers {
+        let vals = t.join().unwrap();
+        // Nothing is inserted while popping, so each thread sees the
+        // entries in order, oldest first among equal keys.
+        assert!(vals.windows(2).all(|w| w[0] < w[1]));
+        popped.extend(vals);
+    }
+    popped.sort_unstable();
+    assert_eq!(popped, (0..8000).collect::<Vec<_>>());
+}
+
+#[test]
+fn purges_keep_every_value_of_a_set() {
+    for duplicates in [Duplicates::Reject, Duplicates::Replace] {
+        let config = Config {
+            purge_threshold: 1,
+            duplicates,
+            ..Config::new(10000)
+        };
+        let pq = std::sync::Arc::new(MDList::<_>::with_config(config).unwrap());
+        // Descending keys land behind the deletion cursor.
+        let producers: Vec<_> = (0..2u32)
+            .map(|t| {
+                let pq = pq.clone();
+                std::thread::spawn(move || {
+                    for i in (0..2000).rev() {
+                        pq.insert(i * 2 + t, i * 2 + t).unwrap();
+                    }
+                })
+            })
+            .collect();
+        let consumers: Vec<_> = (0..2)
+            .map(|_| {
+                let pq = pq.clone();
+                std::thread::spawn(move || {
+                    let mut popped = Vec::new();
+                    for _ in 0..1500 {
+                        popped.extend(pq.pop_min());
+                    }
+                    popped
+                })
+            })
+            .collect();
+        for t in producers {
+            t.join().unwrap();
+        }
+
+        let mut popped: Vec<_> = consumers
+            .into_iter()
+            .flat_map(|t| t.join().unwrap())
+            .collect();
+        while let Some(entry) = pq.pop_min() {
+            popped.push(entry);
+        }
+        popped.sort_unstable();
+        assert_eq!(popped, (0..4000).map(|key| (key, key)).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn prebuilt_list_is_shared() {
+    let entries = (0..4000).map(|i| (i * 2, i * 2));
+    let pq = std::sync::Arc::new(MDList::<_>::from_sorted(Config::new(10000), entries).unwrap());
+    let threads: Vec<_> = (0..4u32)
+        .map(|t| {
+            let pq = pq.clone();
+            std::thread::spawn(move || {
+                let mut popped = Vec::new();
+                for i in 0..1000 {
+                    pq.insert(i * 8 + t * 2 + 1, i * 8 + t * 2 + 1).unwrap();
+                    popped.extend(pq.pop_min());
+                }
+                popped
+            })
+        })
+        .collect();
+
+    let mut popped: Vec<_> = threads.into_iter().flat_map(|t| t.join().unwrap()).collect();
+    popped.extend(std::iter::from_fn(|| pq.pop_min()));
+    popped.sort_unstable();
+    assert_eq!(popped, (0..8000).map(|key| (key, key)).collect::<Vec<_>>());
+}
+
+#[test]
+fn sorted_bulk_inserts_race_with_pops() {
+    let pq = std::sync::Arc::new(MDList::new(1 << 20));
+    let producers: Vec<_> = (0..4u32)
+        .map(|t| {
+            let pq = pq.clone();
+            std::thread::spawn(move || {
+                for chunk in 0..10 {
+                    let keys = (chunk * 200..(chunk + 1) * 200).map(|i| i * 4 + t);
+                    pq.insert_many(keys.map(|key| (key, key))).unwrap();
+                }
+            })
+        })
+        .collect();
+    let consumer = {
+        let pq = pq.clone();
+        std::thread::spawn(move || {
+            let mut popped = Vec::new();
+            for _ in 0..500 {
+                pq.pop_min_n(4, &mut popped);
+            }
+            popped
+        })
+    };
+    for t in producers {
+        t.join().unwrap();
+    }
+
+    let mut popped = consumer.join().unwrap();
+    popped.extend(std::iter::from_fn(|| pq.pop_min()));
+    popped.sort_unstable();
+    assert_eq!(popped, (0..8000).map(|key| (key, key)).collect::<Vec<_>>());
+}
+
+#[test]
+fn removes_race_with_pops() {
+    let pq = std::sync::Arc::new(MDList::new(10000));
+    for key in 0..8000 {
+        pq.insert(key, key).unwrap();
+    }
+    let removers: Vec<_> = (0..2u32)
+        .map(|t| {
+            let pq = pq.clone();
+            std::thread::spawn(move || {
+                (0..4000)
+                    .rev()
+                    .filter_map(|i| pq.remove(i * 2 + t).map(|val| (i * 2 + t, val)))
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    let poppers: Vec<_> = (0..2)
+        .map(|_| {
+            let pq = pq.clone();
+            std::thread::spawn(move || std::iter::from_fn(|| pq.pop_min()).collect::<Vec<_>>())
+        })
+        .collect();
+
+    let mut taken: Vec<_> = removers
+        .into_iter()
+        .chain(poppers)
+        .flat_map(|t| t.join().unwrap())
+        .collect();
+    taken.sort_unstable();
+    assert_eq!(taken, (0..8000).map(|key| (key, key)).collect::<Vec<_>>());
+}
+
+#[test]
+fn priority_changes_race_with_pops() {
+    let pq = std::sync::Arc::new(MDList::new(1 << 20));
+    let entries: Vec<_> = (0..4000u32)
+        .map(|i| pq.insert_entry(1000 + i * 2, i).unwrap())
+        .collect();
+    let changer = {
+        let pq = pq.clone();
+        std::thread::spawn(move || {
+            for (i, entry) in entries.into_iter().enumerate().rev() {
+                let _ = pq.change_priority(entry, i as u32);
+            }
+        })
+    };
+    let poppers: Vec<_> = (0..2)
+        .map(|_| {
+            let pq = pq.clone();
+            std::thread::spawn(move || {
+                let mut popped = Vec::new();
+                while popped.len() < 1500 {
+                    popped.extend(pq.pop_min().map(|(_, val)| val));
+                }
+                popped
+            })
+        })
+        .collect();
+    changer.join().unwrap();
+
+    let mut popped: Vec<_> = poppers.into_iter().flat_map(|t| t.join().unwrap()).collect();
+    popped.extend(std::iter::from_fn(|| pq.pop_min()).map(|(_, val)| val));
+    popped.sort_unstable();
+    assert_eq!(popped, (0..4000).collect::<Vec<_>>());
+}
+
+#[test]
+fn moving_entries_never_go_missing() {
+    for duplicates in [Duplicates::Keep, Duplicates::Reject] {
+        let config = Config {
+            duplicates,
+            ..Config::new(1 << 20)
+        };
+        let pq = std::sync::Arc::new(MDList::<_>::with_config(config).unwrap());
+        pq.insert(1 << 19, 'z').unwrap();
+        let entry = pq.insert_entry(0, 'a').unwrap();
+        let done = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let watchers: Vec<_> = (0..2)
+            .map(|_| {
+                let pq = pq.clone();
+                let done = done.clone();
+                std::thread::spawn(move || {
+                    while !done.load(std::sync::atomic::Ordering::Relaxed) {
+                        // The entry only moves up, so a walk from the head
+                        // meets its old node before its new one.
+                        assert_eq!(pq.peek_min().map(|(_, val)| *val), Some('a'));
+                    }
+                })
+            })
+            .collect();
+
+        let mut entry = entry;
+        for key in 1..20000 {
+            entry = pq.change_priority(entry, key).unwrap();
+        }
+        done.store(true, std::sync::atomic::Ordering::Relaxed);
+        for watcher in watchers {
+            watcher.join().unwrap();
+        }
+        assert_eq!(pq.len(), 2);
+        assert_eq!(pq.pop_min(), Some((19999, 'a')));
+    }
+}
+
+#[test]
+fn updates_race_with_pops() {
+    let pq = std::sync::Arc::new(MDList::new(10000));
+    for key in 0..4000 {
+        pq.insert(key, (key, 0)).unwrap();
+    }
+    let updaters: Vec<_> = (1..3)
+        .map(|round| {
+            let pq = pq.clone();
+            std::thread::spawn(move || {
+                for key in (0..4000).rev() {
+                    if let Ok((old_key, _)) = pq.update_value(key, (key, round)) {
+                        assert_eq!(old_key, key);
+                    }
+                    if let Some(&(got_key, _)) = pq.get(key).as_deref() {
+                        assert_eq!(got_key, key);
+                    }
+                }
+            })
+        })
+        .collect();
+    let popped: Vec<_> = std::iter::from_fn(|| pq.pop_min()).collect();
+    for t in updaters {
+        t.join().unwrap();
+    }
+
+    assert_eq!(popped.len(), 4000);
+    for (key, (val_key, _)) in popped {
+        assert_eq!(key, val_key);
+    }
+}
+
+#[test]
+fn peeks_race_with_pops() {
+    let pq = std::sync::Arc::new(MDList::new(10000));
+    for key in 0..4000 {
+        pq.insert(key, key.to_string()).unwrap();
+    }
+    let peekers: Vec<_> = (0..2)
+        .map(|_| {
+            let pq = pq.clone();
+            std::thread::spawn(move || {
+                while let Some((key, val)) = pq.peek_min() {
+                    assert_eq!(*val, key.to_string());
+                }
+            })
+        })
+        .collect();
+    let poppers: Vec<_> = (0..2)
+        .map(|_| {
+            let pq = pq.clone();
+            std::thread::spawn(move || while pq.pop_min().is_some() {})
+        })
+        .collect();
+    for t in peekers.into_iter().chain(poppers) {
+        t.join().unwrap();
+    }
+    assert_eq!(pq.peek_min_key(), None);
+}
+
+#[test]
+fn conditional_pops_race_with_pops() {
+    let pq = std::sync::Arc::new(MDList::new(10000));
+    for key in 0..8000 {
+        pq.insert(key, key).unwrap();
+    }
+    let threads: Vec<_> = (0..4)
+        .map(|t| {
+            let pq = pq.clone();
+            std::thread::spawn(move || {
+                let mut popped = Vec::new();
+                loop {
+                    let entry = match t {
+                        0 => pq.pop_min(),
+                        1 => pq.pop_min_at_most(6000),
+                        _ => pq.pop_min_if(|key, &val| {
+                            assert_eq!(key, val);
+                            key < 6000
+                        }),
+                    };
+                    match entry {
+                        Some((key, val)) => {
+                            assert_eq!(key, val);
+                            assert!(t == 0 || key <= 6000);
+                            popped.push(key);
+                        }
+                        None if t == 0 => break,
+                        None => {
+                            assert!(pq.peek_min_key().is_none_or(|key| key >= 6000));
+                            break;
+                        }
+                    }
+                }
+                popped
+            })
+        })
+        .collect();
+    let mut popped: Vec<u32> = threads.into_iter().flat_map(|t| t.join().unwrap()).collect();
+    popped.sort_unstable();
+    assert_eq!(popped, (0..8000).collect::<Vec<_>>());
+}
+
+#[test]
+fn prefix_removals_race_with_inserts_and_pops() {
+    let pq = std::sync::Arc::new(MDList::new(100000));
+    let inserters: Vec<_> = (0..2u32)
+        .map(|t| {
+            let pq = pq.clone();
+            std::thread::spawn(move || {
+                for key in (t..40000).step_by(2) {
+                    pq.insert(key, key).unwrap();
+                }
+            })
+        })
+        .collect();
+    let popper = {
+        let pq = pq.clone();
+        std::thread::spawn(move || {
+            let mut popped = Vec::new();
+            for _ in 0..20000 {
+                if let Some((key, val)) = pq.pop_min() {
+                    assert_eq!(key, val);
+                    popped.push(key);
+                }
+            }
+            popped
+        })
+    };
+    let remover = {
+        let pq = pq.clone();
+        std::thread::spawn(move || {
+            let mut removed = Vec::new();
+            for cutoff in (0..40000).step_by(500) {
+                for (key, val) in pq.remove_below(cutoff) {
+                    assert_eq!(key, val);
+                    assert!(key < cutoff);
+                    removed.push(key);
+                }
+            }
+            removed
+        })
+    };
+    for t in inserters {
+        t.join().unwrap();
+    }
+    let mut keys = popper.join().unwrap();
+    keys.extend(remover.join().unwrap());
+    let mut rest = Vec::new();
+    pq.pop_min_n(usize::MAX, &mut rest);
+    keys.extend(rest.into_iter().map(|(key, _)| key));
+    keys.sort_unstable();
+    assert_eq!(keys, (0..40000).collect::<Vec<_>>());
+    assert!(pq.is_empty());
+}
+
+#[test]
+fn filters_race_with_inserts_and_pops() {
+    let pq = std::sync::Arc::new(MDList::new(100000));
+    for key in 0..20000 {
+        pq.insert(key, key).unwrap();
+    }
+    let inserter = {
+        let pq = pq.clone();
+        std::thread::spawn(move || {
+            for key in 20000..40000 {
+                pq.insert(key, key).unwrap();
+            }
+        })
+    };
+    let popper = {
+        let pq = pq.clone();
+        std::thread::spawn(move || {
+            let mut popped = Vec::new();
+            for _ in 0..10000 {
+                if let Some((key, val)) = pq.pop_min() {
+                    assert_eq!(key, val);
+                    popped.push(key);
+                }
+            }
+            popped
+        })
+    };
+    let filter = {
+        let pq = pq.clone();
+        std::thread::spawn(move || {
+            let mut drained = Vec::new();
+            for round in 0..4 {
+                pq.retain(|key, &val| {
+                    assert_eq!(key, val);
+                    key % 7 != round
+                });
+                drained.extend(pq.drain_filter(|key, _| key % 7 == round).map(|(key, _)| key));
+            }
+            drained
+        })
+    };
+    inserter.join().unwrap();
+    let mut keys = popper.join().unwrap();
+    let drained = filter.join().unwrap();
+    assert!(drained.iter().all(|key| key % 7 < 4));
+    keys.extend(drained);
+    let mut rest = Vec::new();
+    pq.pop_min_n(usize::MAX, &mut rest);
+    assert!(rest.iter().all(|&(key, _)| key % 7 >= 4 || key >= 20000));
+    keys.extend(rest.into_iter().map(|(key, _)| key));
+    keys.sort_unstable();
+    let seen = keys.len();
+    keys.dedup();
+    assert_eq!(keys.len(), seen);
+    // Only entries `retain` turned down went missing.
+    let kept: Vec<_> = (0..40000).filter(|key| key % 7 >= 4).collect();
+    assert!(kept.iter().all(|key| keys.binary_search(key).is_ok()));
+    assert_eq!(pq.len(), 0);
+}
+
+#[test]
+fn pops_from_both_ends_race() {
+    let pq = std::sync::Arc::new(MDList::new(10000));
+    let max = std::sync::Arc::new(MaxMDList::new(10000));
+    for key in 0..4000 {
+        pq.insert(key, key).unwrap();
+        max.insert(key, key).unwrap();
+    }
+    let threads: Vec<_> = (0..4)
+        .map(|t| {
+            let pq = pq.clone();
+            let max = max.clone();
+            std::thread::spawn(move || {
+                let mut popped = Vec::new();
+                loop {
+                    let entry = match t {
+                        0 => pq.pop_min(),
+                        1 => pq.pop_max(),
+                        2 => max.pop_max(),
+                        _ => max.pop_min(),
+                    };
+                    match entry {
+                        Some((key, val)) => {
+                            assert_eq!(key, val);
+                            popped.push(key);
+                        }
+                        None => break,
+                    }
+                }
+                // Nothing is inserted meanwhile, so each end is taken in order.
+                if t == 0 || t == 3 {
+                    assert!(popped.windows(2).all(|w| w[0] < w[1]));
+                } else {
+                    assert!(popped.windows(2).all(|w| w[0] > w[1]));
+                }
+                (t / 2, popped)
+            })
+        })
+        .collect();
+    let mut keys = [Vec::new(), Vec::new()];
+    for t in threads {
+        let (list, popped) = t.join().unwrap();
+        keys[list].extend(popped);
+    }
+    for mut keys in keys {
+        keys.sort_unstable();
+        assert_eq!(keys, (0..4000).collect::<Vec<_>>());
+    }
+    assert!(pq.is_empty() && max.is_empty());
+}
+
+#[test]
+fn handles_on_each_thread() {
+    let pq = std::sync::Arc::new(MDList::new(10000));
+    let threads: Vec<_> = (0..4u32)
+        .map(|t| {
+            let pq = pq.clone();
+            std::thread::spawn(move || {
+                let mut handle = pq.handle();
+                let mut popped = Vec::new();
+                for i in 0..1000 {
+                    handle.push(i * 4 + t, t).unwrap();
+                    if i % 2 == 1 {
+                        popped.extend(handle.pop().map(|(key, _)| key));
+                    }
+                }
+                popped
+            })
+        })
+        .collect();
+
+    let mut popped: Vec<_> = threads.into_iter().flat_map(|t| t.join().unwrap()).collect();
+    popped.extend(std::iter::from_fn(|| pq.pop_min()).map(|(key, _)| key));
+    popped.sort_unstable();
+    assert_eq!(popped, (0..4000).collect::<Vec<_>>());
+}
+
+#[test]
+fn walks_see_subtrees_while_they_are_adopted() {
+    for _ in 0..100 {
+        let pq = std::sync::Arc::new(MDList::new(1 << 12));
+        for key in (1..1 << 12).step_by(16) {
+            pq.insert(key, true).unwrap();
+        }
+        let inserters: Vec<_> = (0..3u32)
+            .map(|t| {
+                let pq = pq.clone();
+                std::thread::spawn(move || {
+                    // A key ending in a zero digit takes over the subtrees of
+                    // the node after it, where the kept keys hang.
+                    for key in (0..1 << 12).rev().filter(|key| key % 16 == 0 && key % 3 == t) {
+                        pq.insert(key, false).unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        while !inserters.iter().all(|t| t.is_finished()) {
+            let kept = pq.range(..).filter(|&(_, kept)| kept).count();
+            assert_eq!(kept, 1 << 8);
+        }
+        for t in inserters {
+            t.join().unwrap();
+        }
+    }
+}
+
+#[test]
+fn equal_keys_churn_through_one_chain() {
+    let pq = std::sync::Arc::new(MDList::new(4));
+    let threads: Vec<_> = (0..4u32)
+        .map(|t| {
+            let pq = pq.clone();
+            std::thread::spawn(move || {
+                let mut popped = Vec::new();
+                for i in 0..5000 {
+                    pq.insert(1, t * 5000 + i).unwrap();
+                    popped.extend(pq.pop_min().map(|(_, val)| val));
+                }
+                popped
+            })
+        })
+        .collect();
+
+    let mut popped: Vec<_> = threads.into_iter().flat_map(|t| t.join().unwrap()).collect();
+    popped.extend(std::iter::from_fn(|| pq.pop_min()).map(|(_, val)| val));
+    popped.sort_unstable();
+    assert_eq!(popped, (0..20000).collect::<Vec<_>>());
+}
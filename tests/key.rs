@@ -0,0 +1,93 @@
+use lockprio::PriorityKey;
+use std::cmp::Reverse;
+use std::fmt::Debug;
+
+fn assert_order_kept<T: PriorityKey + Debug>(sorted: &[T], eq: impl Fn(&T, &T) -> bool) {
+    for pair in sorted.windows(2) {
+        assert!(
+            pair[0].encode() < pair[1].encode(),
+            "{:?} must encode below {:?}",
+            pair[0],
+            pair[1]
+        );
+    }
+    for &value in sorted {
+        assert!(
+            eq(&T::decode(value.encode()), &value),
+            "{:?} must survive a round trip",
+            value
+        );
+    }
+}
+
+#[test]
+fn signed_keys() {
+    assert_order_kept(&[i32::MIN, -70_000, -1, 0, 1, 70_000, i32::MAX], |a, b| {
+        a == b
+    });
+    assert_order_kept(
+        &[i64::MIN, -(1 << 40), -1, 0, 1, 1 << 40, i64::MAX],
+        |a, b| a == b,
+    );
+}
+
+#[test]
+fn float_keys_follow_total_order() {
+    let floats = [
+        -f64::NAN,
+        f64::NEG_INFINITY,
+        -1e300,
+        -1.5,
+        -f64::MIN_POSITIVE,
+        -0.0,
+        0.0,
+        f64::MIN_POSITIVE,
+        1.5,
+        1e300,
+        f64::INFINITY,
+        f64::NAN,
+    ];
+    for pair in floats.windows(2) {
+        assert!(pair[0].total_cmp(&pair[1]).is_lt());
+    }
+    assert_order_kept(&floats, |a, b| a.to_bits() == b.to_bits());
+
+    let floats = [
+        -f32::NAN,
+        f32::NEG_INFINITY,
+        -1.5,
+        -0.0,
+        0.0,
+        f32::MIN_POSITIVE,
+        f32::INFINITY,
+        f32::NAN,
+    ];
+    assert_order_kept(&floats, |a, b| a.to_bits() == b.to_bits());
+}
+
+#[test]
+fn reverse_keys() {
+    assert_order_kept(&[Reverse(u32::MAX), Reverse(7), Reverse(0)], |a, b| a == b);
+    assert_order_kept(&[Reverse(5i64), Reverse(-5)], |a, b| a == b);
+    assert_order_kept(
+        &[Reverse((2u8, 0u8)), Reverse((1, 9)), Reverse((1, 3))],
+        |a, b| a == b,
+    );
+}
+
+#[test]
+fn tuple_keys_are_lexicographic() {
+    assert_order_kept(&[(-1i32, u32::MAX), (0, 0), (0, 1), (1, 0)], |a, b| a == b);
+    assert_order_kept(
+        &[
+            (0u8, Reverse(9u16), -3i64),
+            (0, Reverse(9), 2),
+            (0, Reverse(1), i64::MIN),
+            (1, Reverse(u16::MAX), 0),
+        ],
+        |a, b| a == b,
+    );
+    assert_order_kept(&[((0u8, 5u8), 1u32), ((0, 6), 0), ((1, 0), 0)], |a, b| {
+        a == b
+    });
+}
@@ -0,0 +1,985 @@
+use lockprio::{BuildError, Config, ConfigError, Duplicates, InsertError, MDList, MaxMDList};
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+struct DropCounter(Arc<AtomicUsize>);
+
+impl Drop for DropCounter {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn pop_min_returns_owned_values() {
+    let pq = MDList::new(10000);
+    pq.insert(30, String::from("c")).unwrap();
+    pq.insert(10, String::from("a")).unwrap();
+    pq.insert(20, String::from("b")).unwrap();
+
+    assert_eq!(pq.pop_min(), Some((10, String::from("a"))));
+    assert_eq!(pq.pop_min(), Some((20, String::from("b"))));
+    assert_eq!(pq.pop_min(), Some((30, String::from("c"))));
+    assert_eq!(pq.pop_min(), None);
+}
+
+#[test]
+fn values_dropped_with_queue() {
+    let drops = Arc::new(AtomicUsize::new(0));
+    let pq = MDList::new(10000);
+    for key in 0..10 {
+        pq.insert(key, DropCounter(drops.clone())).unwrap();
+    }
+
+    drop(pq.pop_min());
+    assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+    drop(pq);
+    assert_eq!(drops.load(Ordering::SeqCst), 10);
+}
+
+#[test]
+fn values_dropped_once_across_purges() {
+    let drops = Arc::new(AtomicUsize::new(0));
+    let config = Config {
+        purge_threshold: 3,
+        ..Config::new(10000)
+    };
+    let pq = MDList::<_>::with_config(config).unwrap();
+    for key in 0..100 {
+        pq.insert(key % 40, DropCounter(drops.clone())).unwrap();
+    }
+
+    for _ in 0..60 {
+        drop(pq.pop_min());
+    }
+    assert_eq!(drops.load(Ordering::SeqCst), 60);
+
+    drop(pq);
+    assert_eq!(drops.load(Ordering::SeqCst), 100);
+}
+
+#[test]
+fn pops_in_key_order() {
+    let pq = MDList::new(1 << 28);
+    let mut keys: Vec<u32> = Vec::new();
+    let mut x: u32 = 12345;
+    for _ in 0..2000 {
+        x = x.wrapping_mul(1103515245).wrapping_add(12345);
+        keys.push(x >> 4);
+    }
+    keys.sort_unstable();
+    keys.dedup();
+
+    for (i, &key) in keys.iter().enumerate().rev() {
+        pq.insert(key, i).unwrap();
+    }
+
+    for (i, &key) in keys.iter().enumerate() {
+        assert_eq!(pq.pop_min(), Some((key, i)));
+    }
+    assert_eq!(pq.pop_min(), None);
+}
+
+#[test]
+fn handle_push_pop_peek() {
+    let pq = MDList::new(10000);
+    let mut handle = pq.handle();
+    assert_eq!(handle.peek(), None);
+
+    handle.push(7, "seven").unwrap();
+    handle.push(3, "three").unwrap();
+    pq.insert(5, "five").unwrap();
+    assert_eq!(handle.peek(), Some(3));
+
+    assert_eq!(handle.pop(), Some((3, "three")));
+    assert_eq!(handle.peek(), Some(5));
+    assert_eq!(pq.pop_min(), Some((5, "five")));
+    assert_eq!(handle.pop(), Some((7, "seven")));
+    assert_eq!(handle.pop(), None);
+}
+
+#[test]
+fn peek_min_leaves_entry_in_place() {
+    let pq = MDList::new(10000);
+    assert!(pq.peek_min().is_none());
+    assert_eq!(pq.peek_min_key(), None);
+
+    pq.insert(8, String::from("first")).unwrap();
+    pq.insert(8, String::from("second")).unwrap();
+    pq.insert(9, String::from("third")).unwrap();
+    assert_eq!(pq.peek_min_key(), Some(8));
+    assert_eq!(pq.peek_min().map(|(key, val)| (key, val.clone())), Some((8, String::from("first"))));
+    assert_eq!(pq.pop_min(), Some((8, String::from("first"))));
+    assert_eq!(pq.peek_min().map(|(key, val)| (key, val.clone())), Some((8, String::from("second"))));
+    assert_eq!(pq.pop_min(), Some((8, String::from("second"))));
+    assert_eq!(pq.peek_min().map(|(key, val)| (key, val.clone())), Some((9, String::from("third"))));
+}
+
+/// Counts its clones and drops in the shared counters.
+#[derive(Debug)]
+struct Counted(u32, Arc<(AtomicUsize, AtomicUsize)>);
+
+impl Clone for Counted {
+    fn clone(&self) -> Self {
+        self.1 .0.fetch_add(1, Ordering::SeqCst);
+        Counted(self.0, self.1.clone())
+    }
+}
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        self.1 .1.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn borrowed_values_do_not_block_pops() {
+    let counts = Arc::new((AtomicUsize::new(0), AtomicUsize::new(0)));
+    let pq = MDList::new(10000);
+    pq.insert(3, Counted(3, counts.clone())).unwrap();
+    pq.insert(5, Counted(5, counts.clone())).unwrap();
+
+    let (key, peeked) = pq.peek_min().unwrap();
+    let got = pq.get(3).unwrap();
+    assert_eq!(key, 3);
+    // The pop takes a clone rather than wait for the borrows.
+    let (key, popped) = pq.pop_min().unwrap();
+    assert_eq!((key, popped.0), (3, 3));
+    assert_eq!(counts.0.load(Ordering::SeqCst), 1);
+    assert_eq!((peeked.0, got.0), (3, 3));
+    drop(popped);
+    drop(peeked);
+    assert_eq!(counts.1.load(Ordering::SeqCst), 1);
+    // The last borrow drops the value left behind.
+    drop(got);
+    assert_eq!(counts.1.load(Ordering::SeqCst), 2);
+
+    // Unborrowed values are moved out, not cloned.
+    drop(pq.pop_min());
+    assert_eq!(counts.0.load(Ordering::SeqCst), 1);
+    assert_eq!(counts.1.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn len_and_is_empty() {
+    let config = Config {
+        purge_threshold: 3,
+        duplicates: Duplicates::Replace,
+        ..Config::new(10000)
+    };
+    let pq = MDList::<_>::with_config(config).unwrap();
+    assert!(pq.is_empty());
+    assert_eq!(pq.len(), 0);
+
+    for key in 0..10 {
+        pq.insert(key, key).unwrap();
+    }
+    pq.insert(4, 40).unwrap();
+    assert_eq!(pq.len(), 10);
+
+    for _ in 0..5 {
+        pq.pop_min();
+    }
+    // Goes in behind the deletion cursor.
+    pq.insert(1, 1).unwrap();
+    assert!(!pq.is_empty());
+    assert_eq!(pq.len(), 6);
+
+    while pq.pop_min().is_some() {}
+    assert!(pq.is_empty());
+    assert_eq!(pq.len(), 0);
+}
+
+#[test]
+fn pop_min_n_takes_a_sorted_batch() {
+    let config = Config {
+        purge_threshold: 8,
+        ..Config::new(10000)
+    };
+    let pq = MDList::<_>::with_config(config).unwrap();
+    for key in (0..30).rev() {
+        pq.insert(key / 2, key).unwrap();
+    }
+
+    let mut out = Vec::new();
+    assert_eq!(pq.pop_min_n(0, &mut out), 0);
+    assert_eq!(pq.pop_min_n(5, &mut out), 5);
+    assert_eq!(pq.handle().pop_n(10, &mut out), 10);
+    assert_eq!(pq.pop_min_n(100, &mut out), 15);
+    assert_eq!(pq.pop_min_n(100, &mut out), 0);
+
+    let keys: Vec<_> = out.iter().map(|&(key, _)| key).collect();
+    assert_eq!(keys, (0..30).map(|i| i / 2).collect::<Vec<_>>());
+    out.sort_unstable();
+    assert_eq!(out, (0..30).map(|i| (i / 2, i)).collect::<Vec<_>>());
+    assert!(pq.is_empty());
+}
+
+#[test]
+fn conditional_pops_only_take_the_minimum() {
+    let pq = MDList::<_>::new(1000);
+    for key in [40, 10, 30, 10, 20] {
+        pq.insert(key, key * 10 + pq.len() as u32).unwrap();
+    }
+
+    assert_eq!(pq.pop_min_at_most(5), None);
+    assert_eq!(pq.pop_min_at_most(10), Some((10, 101)));
+    assert_eq!(pq.pop_min_at_most(10), Some((10, 103)));
+    assert_eq!(pq.pop_min_at_most(10), None);
+
+    // A predicate sees the oldest entry of the smallest key, never past it.
+    let mut seen = Vec::new();
+    assert_eq!(
+        pq.pop_min_if(|key, &val| {
+            seen.push(key);
+            val % 2 == 1
+        }),
+        None
+    );
+    assert_eq!(seen, [20]);
+    assert_eq!(pq.pop_min_if(|key, _| key == 20), Some((20, 204)));
+    assert_eq!(pq.pop_min_at_most(1000), Some((30, 302)));
+    assert_eq!(pq.len(), 1);
+    assert_eq!(pq.pop_min_if(|_, _| true), Some((40, 400)));
+    assert_eq!(pq.pop_min_if(|_, _| true), None);
+}
+
+#[test]
+fn conditional_pops_do_not_hold_up_other_pops() {
+    let pq = MDList::<_>::new(1000);
+    pq.insert(1, 10).unwrap();
+    pq.insert(2, 20).unwrap();
+
+    // A pop from another thread finishes while the predicate is running and
+    // takes the entry it was shown, so the next one is offered instead.
+    let mut seen = Vec::new();
+    let popped = pq.pop_min_if(|key, &val| {
+        if seen.is_empty() {
+            let other = std::thread::scope(|s| s.spawn(|| pq.pop_min()).join().unwrap());
+            assert_eq!(other, Some((1, 10)));
+        }
+        seen.push((key, val));
+        true
+    });
+    assert_eq!(popped, Some((2, 20)));
+    assert_eq!(seen, [(1, 10), (2, 20)]);
+
+    pq.insert(3, 30).unwrap();
+    let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        pq.pop_min_if(|_, _| panic!("predicate"))
+    }));
+    assert!(panicked.is_err());
+    assert_eq!(pq.pop_min(), Some((3, 30)));
+}
+
+#[test]
+fn remove_below_drops_a_prefix() {
+    let pq = MDList::<_>::new(10000);
+    for key in (0..200).rev() {
+        pq.insert(key / 2 * 10, key).unwrap();
+    }
+    assert_eq!(pq.pop_min(), Some((0, 1)));
+
+    assert_eq!(pq.remove_below(0), []);
+    let removed = pq.remove_below(55);
+    let expected: Vec<_> = (1..12).map(|key| (key / 2 * 10, key ^ 1)).collect();
+    assert_eq!(removed, expected);
+    assert_eq!(pq.len(), 188);
+    assert_eq!(pq.peek_min_key(), Some(60));
+
+    // The list keeps working below the cutoff after the purge.
+    pq.insert(5, 5).unwrap();
+    assert_eq!(pq.pop_min(), Some((5, 5)));
+    assert_eq!(pq.remove_below(61), [(60, 13), (60, 12)]);
+    assert_eq!(pq.pop_min(), Some((70, 15)));
+
+    pq.clear();
+    assert!(pq.is_empty());
+    assert_eq!(pq.len(), 0);
+    assert_eq!(pq.pop_min(), None);
+    pq.insert(0, 1).unwrap();
+    pq.clear();
+    pq.clear();
+    assert_eq!(pq.pop_min(), None);
+}
+
+#[test]
+fn retain_and_drain_filter() {
+    let pq = MDList::<_>::new(1000);
+    for key in 0..100 {
+        pq.insert(key % 50, key).unwrap();
+    }
+
+    pq.retain(|key, &val| key % 5 != 0 && val < 90);
+    assert_eq!(pq.len(), 72);
+    assert!(!pq.contains(10));
+    assert_eq!(pq.get(41).as_deref(), Some(&41));
+    assert_eq!(pq.remove(41), Some(41));
+    assert_eq!(pq.remove(41), None);
+
+    {
+        let mut drain = pq.drain_filter(|key, &val| key < 3 || val == 48);
+        assert_eq!(drain.next(), Some((1, 1)));
+    }
+    // Dropping the iterator leaves what it has not yielded, even under a
+    // key it has started on.
+    assert_eq!(pq.peek_min().map(|(key, val)| (key, *val)), Some((1, 51)));
+
+    let drained: Vec<_> = pq.drain_filter(|key, &val| key < 3 || val == 48).collect();
+    assert_eq!(drained, [(1, 51), (2, 2), (2, 52), (48, 48)]);
+    assert_eq!(pq.len(), 66);
+    assert_eq!(pq.pop_min(), Some((3, 3)));
+
+    let all: Vec<_> = pq.drain_filter(|_, _| true).collect();
+    assert_eq!(all.len(), 65);
+    assert!(all.windows(2).all(|w| w[0].0 <= w[1].0));
+    assert!(pq.is_empty());
+    assert_eq!(pq.drain_filter(|_, _| true).next(), None);
+}
+
+#[test]
+fn pop_max_takes_from_the_top() {
+    let pq = MDList::<_>::new(1000);
+    for key in [5, 999, 0, 500, 999, 20] {
+        pq.insert(key, pq.len()).unwrap();
+    }
+
+    assert_eq!(pq.pop_max(), Some((999, 1)));
+    assert_eq!(pq.pop_max(), Some((999, 4)));
+    assert_eq!(pq.pop_min(), Some((0, 2)));
+    assert_eq!(pq.pop_max(), Some((500, 3)));
+    assert_eq!(pq.len(), 2);
+    assert_eq!(pq.pop_max(), Some((20, 5)));
+    assert_eq!(pq.pop_max(), Some((5, 0)));
+    assert_eq!(pq.pop_max(), None);
+    assert!(pq.is_empty());
+}
+
+#[test]
+fn pop_max_purges_what_it_empties() {
+    let config = Config {
+        purge_threshold: 1,
+        ..Config::new(1000)
+    };
+    let pq = MDList::<_>::with_config(config).unwrap();
+    for round in 0..3 {
+        for key in (0..200).step_by(3) {
+            pq.insert(key, round).unwrap();
+        }
+        for key in (0..200).step_by(3).rev() {
+            assert_eq!(pq.pop_max(), Some((key, round)));
+        }
+        assert_eq!(pq.pop_max(), None);
+        assert!(pq.is_empty());
+    }
+    pq.insert(7, 7).unwrap();
+    assert_eq!(pq.pop_min(), Some((7, 7)));
+}
+
+#[test]
+fn max_list_mirrors_keys_within_its_range() {
+    let pq = MaxMDList::new(1000);
+    for key in [5, 999, 0, 500, 5] {
+        pq.insert(key, key * 10 + pq.len() as u32).unwrap();
+    }
+    assert_eq!(
+        pq.insert(1000, 0),
+        Err(InsertError::KeyOutOfRange {
+            key: 1000,
+            range: 1000
+        })
+    );
+
+    assert_eq!(pq.peek_max_key(), Some(999));
+    assert_eq!(pq.peek_max().map(|(key, val)| (key, *val)), Some((999, 9991)));
+    assert!(pq.contains(500));
+    assert!(!pq.contains(1000));
+    assert_eq!(pq.get(5).as_deref(), Some(&50));
+    assert_eq!(pq.pop_max(), Some((999, 9991)));
+    assert_eq!(pq.pop_min(), Some((0, 2)));
+    assert_eq!(pq.remove(500), Some(5003));
+    assert_eq!(pq.len(), 2);
+
+    let mut out = Vec::new();
+    assert_eq!(pq.pop_max_n(10, &mut out), 2);
+    assert_eq!(out, [(5, 50), (5, 54)]);
+    assert!(pq.is_empty());
+
+    let pq = MaxMDList::<_, i16, 4, 16>::with_config(Config::default()).unwrap();
+    for key in [-3, i16::MAX, 7, i16::MIN] {
+        pq.insert(key, ()).unwrap();
+    }
+    let mut out = Vec::new();
+    pq.pop_max_n(10, &mut out);
+    let keys: Vec<_> = out.into_iter().map(|(key, _)| key).collect();
+    assert_eq!(keys, [i16::MAX, 7, -3, i16::MIN]);
+}
+
+#[test]
+fn insert_many_sorted_and_unsorted() {
+    let pq = MDList::new(1 << 20);
+    pq.insert_many((0..3000).map(|i| (i * 37, i))).unwrap();
+    pq.insert_many((0..3000).rev().map(|i| (i * 37 + 1, i))).unwrap();
+    pq.insert_many([(5, 0), (5, 1), (2, 2)]).unwrap();
+
+    let mut expected: Vec<_> = (0..3000)
+        .flat_map(|i| [(i * 37, i), (i * 37 + 1, i)])
+        .chain([(2, 2), (5, 0), (5, 1)])
+        .collect();
+    expected.sort_by_key(|&(key, _)| key);
+    let popped: Vec<_> = std::iter::from_fn(|| pq.pop_min()).collect();
+    assert_eq!(popped, expected);
+}
+
+#[test]
+fn insert_many_stops_at_rejected_key() {
+    let pq = MDList::new(100);
+    assert_eq!(
+        pq.insert_many([(1, 'a'), (200, 'b'), (2, 'c')]),
+        Err(InsertError::KeyOutOfRange { key: 200, range: 100 })
+    );
+    assert_eq!(pq.pop_min(), Some((1, 'a')));
+    assert_eq!(pq.pop_min(), None);
+}
+
+#[test]
+fn from_sorted_builds_a_usable_list() {
+    let config = Config {
+        purge_threshold: 16,
+        ..Config::new(1 << 20)
+    };
+    let entries = (0..5000u32).map(|i| (i / 3 * 7, i));
+    let pq = MDList::<_>::from_sorted(config, entries).unwrap();
+    assert_eq!(pq.len(), 5000);
+
+    // Later inserts land between and before the prebuilt nodes.
+    for key in [1, 8, 3000, 11662] {
+        pq.insert(key, u32::MAX).unwrap();
+    }
+    let mut expected: Vec<_> = (0..5000).map(|i| (i / 3 * 7, i)).collect();
+    expected.extend([1, 8, 3000, 11662].map(|key| (key, u32::MAX)));
+    expected.sort_by_key(|&(key, _)| key);
+    let popped: Vec<_> = std::iter::from_fn(|| pq.pop_min()).collect();
+    assert_eq!(popped, expected);
+}
+
+#[test]
+fn from_sorted_falls_back_on_unsorted_input() {
+    let entries = [(0, 'a'), (4, 'b'), (4, 'c'), (2, 'd'), (9, 'e'), (1, 'f')];
+    let pq = MDList::<_>::from_sorted(Config::new(100), entries).unwrap();
+    let popped: String = std::iter::from_fn(|| pq.pop_min()).map(|(_, val)| val).collect();
+    assert_eq!(popped, "afdbce");
+
+    let config = Config {
+        duplicates: Duplicates::Reject,
+        ..Config::new(100)
+    };
+    assert_eq!(
+        MDList::<_>::from_sorted(config, [(1, ()), (1, ())]).err(),
+        Some(BuildError::Insert(InsertError::DuplicateKey { key: 1 }))
+    );
+    assert_eq!(
+        MDList::<_>::from_sorted(Config::new(100), [(100, ())]).err(),
+        Some(BuildError::Insert(InsertError::KeyOutOfRange { key: 100, range: 100 }))
+    );
+}
+
+#[test]
+fn collect_and_extend() {
+    let mut pq: MDList<char> = [(3, 'c'), (1, 'a')].into_iter().collect();
+    pq.extend([(2, 'b'), (u32::MAX, 'z')]);
+    let popped: String = std::iter::from_fn(|| pq.pop_min()).map(|(_, val)| val).collect();
+    assert_eq!(popped, "abcz");
+}
+
+#[test]
+fn remove_arbitrary_keys() {
+    let config = Config {
+        purge_threshold: 4,
+        ..Config::new(10000)
+    };
+    let pq = MDList::<_>::with_config(config).unwrap();
+    for key in 0..100 {
+        pq.insert(key, key).unwrap();
+    }
+    pq.insert(50, 500).unwrap();
+
+    assert_eq!(pq.remove(0), Some(0));
+    assert_eq!(pq.remove(50), Some(50));
+    assert_eq!(pq.remove(77), Some(77));
+    assert_eq!(pq.remove(77), None);
+    assert_eq!(pq.remove(200), None);
+    assert_eq!(pq.remove(10000), None);
+    assert_eq!(pq.len(), 98);
+
+    let popped: Vec<_> = std::iter::from_fn(|| pq.pop_min()).collect();
+    let expected: Vec<_> = (1..100)
+        .filter(|&key| key != 77)
+        .map(|key| (key, if key == 50 { 500 } else { key }))
+        .collect();
+    assert_eq!(popped, expected);
+    assert!(pq.is_empty());
+}
+
+#[test]
+fn change_priority_moves_one_entry() {
+    let config = Config {
+        purge_threshold: 2,
+        ..Config::new(10000)
+    };
+    let pq = MDList::<_>::with_config(config).unwrap();
+    let a = pq.insert_entry(50, 'a').unwrap();
+    let b = pq.insert_entry(50, 'b').unwrap();
+    for key in 0..10 {
+        pq.insert(key, '-').unwrap();
+    }
+    assert_eq!(a.key(), 50);
+
+    let b = pq.change_priority(b, 5).unwrap();
+    assert_eq!(b.key(), 5);
+    // Purges on the way down move `a` and `b` onto new nodes.
+    for _ in 0..6 {
+        assert_eq!(pq.pop_min().map(|(_, val)| val), Some('-'));
+    }
+    let b = pq.change_priority(b, 70).unwrap();
+    assert_eq!(
+        pq.change_priority(a, 10000),
+        Err(InsertError::KeyOutOfRange { key: 10000, range: 10000 })
+    );
+    let a = pq.change_priority(a, 60).unwrap();
+
+    let popped: Vec<_> = std::iter::from_fn(|| pq.pop_min()).collect();
+    assert_eq!(popped[4..], [(60, 'a'), (70, 'b')]);
+    assert_eq!(pq.change_priority(a, 1), Err(InsertError::EntryNotFound));
+    assert_eq!(pq.change_priority(b, 1), Err(InsertError::EntryNotFound));
+    assert!(pq.is_empty());
+}
+
+#[test]
+fn change_priority_in_a_set() {
+    for duplicates in [Duplicates::Reject, Duplicates::Replace] {
+        let config = Config {
+            duplicates,
+            ..Config::new(10000)
+        };
+        let pq = MDList::<_>::with_config(config).unwrap();
+        let a = pq.insert_entry(1, 'a').unwrap();
+        pq.insert(2, 'b').unwrap();
+
+        // A move never replaces an entry, and a failed one leaves its own.
+        assert_eq!(pq.change_priority(a, 2), Err(InsertError::DuplicateKey { key: 2 }));
+        assert_eq!(pq.len(), 2);
+        assert_eq!(pq.get(1).as_deref(), Some(&'a'));
+        assert_eq!(pq.change_priority(a, 1), Ok(a));
+        let a = pq.change_priority(a, 3).unwrap();
+        assert_eq!(pq.pop_min(), Some((2, 'b')));
+        assert_eq!(pq.pop_min(), Some((3, 'a')));
+        assert_eq!(pq.pop_min(), None);
+        assert_eq!(pq.change_priority(a, 4), Err(InsertError::EntryNotFound));
+    }
+}
+
+#[test]
+fn stale_handles_move_nothing() {
+    for duplicates in [Duplicates::Keep, Duplicates::Reject, Duplicates::Replace] {
+        let config = Config {
+            duplicates,
+            ..Config::new(10000)
+        };
+        let pq = MDList::<_>::with_config(config).unwrap();
+        let other = MDList::<_>::with_config(config).unwrap();
+
+        // A later entry under the same key, possibly on the same node, is
+        // not the one the handle names.
+        let a = pq.insert_entry(1, 'a').unwrap();
+        assert_eq!(pq.pop_min(), Some((1, 'a')));
+        pq.insert(1, 'b').unwrap();
+        assert_eq!(pq.change_priority(a, 5), Err(InsertError::EntryNotFound));
+
+        // Nor is an entry of another list with the same key and stamp.
+        let c = other.insert_entry(7, 'c').unwrap();
+        let d = pq.insert_entry(7, 'd').unwrap();
+        assert_eq!(pq.change_priority(c, 8), Err(InsertError::EntryNotFound));
+        assert_eq!(other.change_priority(d, 8), Err(InsertError::EntryNotFound));
+
+        // Updating an entry keeps it, so its handle stays valid.
+        assert_eq!(pq.update_value(7, 'e'), Ok('d'));
+        let e = pq.change_priority(d, 9).unwrap();
+        assert_eq!(pq.get(9).as_deref(), Some(&'e'));
+        assert_eq!(pq.pop_min(), Some((1, 'b')));
+        assert_eq!(pq.pop_min(), Some((9, 'e')));
+        assert_eq!(pq.pop_min(), None);
+        assert_eq!(pq.change_priority(e, 3), Err(InsertError::EntryNotFound));
+    }
+
+    // An entry replaced by an insertion under its key is gone.
+    let config = Config {
+        duplicates: Duplicates::Replace,
+        ..Config::new(10000)
+    };
+    let pq = MDList::<_>::with_config(config).unwrap();
+    let a = pq.insert_entry(1, 'a').unwrap();
+    let b = pq.insert_entry(1, 'b').unwrap();
+    assert_eq!(pq.change_priority(a, 2), Err(InsertError::EntryNotFound));
+    assert_eq!(pq.change_priority(b, 2).map(|b| b.key()), Ok(2));
+    assert_eq!(pq.pop_min(), Some((2, 'b')));
+}
+
+#[test]
+fn dictionary_lookups() {
+    let pq = MDList::new(10000);
+    for key in (0..100).step_by(3) {
+        pq.insert(key, key.to_string()).unwrap();
+    }
+    pq.insert(30, String::from("thirty")).unwrap();
+
+    assert!(pq.contains(0) && pq.contains(99) && pq.contains(30));
+    assert!(!pq.contains(1) && !pq.contains(100) && !pq.contains(10000));
+    assert_eq!(pq.get(33).as_deref(), Some(&String::from("33")));
+    assert!(pq.get(34).is_none());
+
+    assert_eq!(pq.update_value(30, String::from("x")), Ok(String::from("30")));
+    assert_eq!(pq.get(30).as_deref(), Some(&String::from("x")));
+    assert_eq!(pq.update_value(31, String::from("y")), Err(String::from("y")));
+    assert_eq!(pq.update_value(20000, String::from("z")), Err(String::from("z")));
+
+    assert_eq!(pq.remove(30), Some(String::from("x")));
+    assert_eq!(pq.get(30).as_deref(), Some(&String::from("thirty")));
+    assert_eq!(pq.remove(30), Some(String::from("thirty")));
+    assert!(!pq.contains(30));
+    assert_eq!(pq.update_value(30, String::from("w")), Err(String::from("w")));
+}
+
+#[test]
+fn range_lists_entries_in_order() {
+    let config = Config {
+        purge_threshold: 4,
+        ..Config::new(10000)
+    };
+    let pq = MDList::<_>::with_config(config).unwrap();
+    for key in (0..200).step_by(5) {
+        pq.insert(key, key).unwrap();
+    }
+    pq.insert(50, 500).unwrap();
+    for _ in 0..6 {
+        pq.pop_min();
+    }
+    pq.remove(60);
+
+    let collect = |range: std::ops::Range<u32>| pq.range(range).collect::<Vec<_>>();
+    assert_eq!(
+        collect(0..56),
+        [(30, 30), (35, 35), (40, 40), (45, 45), (50, 50), (50, 500), (55, 55)]
+    );
+    assert_eq!(pq.range(185..=195).collect::<Vec<_>>(), [(185, 185), (190, 190), (195, 195)]);
+    assert_eq!(pq.range(56..66).collect::<Vec<_>>(), [(65, 65)]);
+    assert_eq!(pq.range(196..).count(), 0);
+    assert_eq!(pq.range(..).count(), 34);
+    assert_eq!(collect(61..61), []);
+}
+
+#[test]
+fn successor_and_predecessor_match_a_sorted_set() {
+    let pq = MDList::<_>::with_config(Config::new(1 << 20)).unwrap();
+    let mut keys = std::collections::BTreeSet::new();
+    let mut x: u32 = 99;
+    for _ in 0..3000 {
+        x = x.wrapping_mul(1103515245).wrapping_add(12345);
+        let key = (x >> 8) % (1 << 20);
+        pq.insert(key, ()).unwrap();
+        keys.insert(key);
+    }
+    for _ in 0..500 {
+        let (key, ()) = pq.pop_min().unwrap();
+        keys.remove(&key);
+    }
+    for _ in 0..500 {
+        x = x.wrapping_mul(1103515245).wrapping_add(12345);
+        let key = (x >> 8) % (1 << 20);
+        if pq.remove(key).is_some() {
+            keys.remove(&key);
+        }
+    }
+
+    for probe in (0..1 << 20).step_by(997).chain([0, (1 << 20) - 1]) {
+        assert_eq!(pq.successor(probe), keys.range(probe + 1..).next().copied());
+        assert_eq!(pq.predecessor(probe), keys.range(..probe).next_back().copied());
+    }
+    assert_eq!(pq.successor(u32::MAX), None);
+}
+
+#[test]
+fn neighbours_with_custom_shape() {
+    let pq = MDList::<(), u32, 3, 10>::with_config(Config::new(1000)).unwrap();
+    for key in [0, 7, 70, 700, 707, 999] {
+        pq.insert(key, ()).unwrap();
+    }
+    assert_eq!(pq.successor(0), Some(7));
+    assert_eq!(pq.successor(8), Some(70));
+    assert_eq!(pq.successor(71), Some(700));
+    assert_eq!(pq.successor(707), Some(999));
+    assert_eq!(pq.predecessor(1), Some(0));
+    assert_eq!(pq.predecessor(0), None);
+    assert_eq!(pq.predecessor(700), Some(70));
+    assert_eq!(pq.predecessor(998), Some(707));
+    let keys: Vec<_> = pq.range(1..=999).map(|(key, ())| key).collect();
+    assert_eq!(keys, [7, 70, 700, 707, 999]);
+}
+
+#[test]
+fn duplicate_keys_are_kept() {
+    let pq = MDList::new(10000);
+    pq.insert(0, 'a').unwrap();
+    pq.insert(5, 'b').unwrap();
+    pq.insert(0, 'c').unwrap();
+    pq.insert(5, 'd').unwrap();
+
+    let mut popped: Vec<_> = std::iter::from_fn(|| pq.pop_min()).collect();
+    assert_eq!(popped.iter().map(|(k, _)| *k).collect::<Vec<_>>(), [0, 0, 5, 5]);
+    popped.sort();
+    assert_eq!(popped, [(0, 'a'), (0, 'c'), (5, 'b'), (5, 'd')]);
+}
+
+#[test]
+fn equal_keys_pop_in_insertion_order() {
+    let config = Config {
+        purge_threshold: 2,
+        ..Config::new(10000)
+    };
+    let pq = MDList::<_>::with_config(config).unwrap();
+    for i in 0..20 {
+        pq.insert(1 + i % 2 * 7, i).unwrap();
+    }
+    // The purge after the second pop moves the rest of the entries under
+    // key 1 into the new head.
+    assert_eq!(pq.pop_min(), Some((1, 0)));
+    assert_eq!(pq.pop_min(), Some((1, 2)));
+    pq.insert(1, 20).unwrap();
+
+    let popped: Vec<_> = std::iter::from_fn(|| pq.pop_min()).collect();
+    let expected: Vec<_> = (4..21)
+        .step_by(2)
+        .map(|i| (1, i))
+        .chain((1..20).step_by(2).map(|i| (8, i)))
+        .collect();
+    assert_eq!(popped, expected);
+}
+
+#[test]
+fn set_rejects_duplicates() {
+    let config = Config {
+        duplicates: Duplicates::Reject,
+        ..Config::new(10000)
+    };
+    let pq = MDList::<_>::with_config(config).unwrap();
+    assert_eq!(pq.insert(0, 'a'), Ok(()));
+    assert_eq!(pq.insert(4, 'b'), Ok(()));
+    assert_eq!(pq.insert(4, 'c'), Err(InsertError::DuplicateKey { key: 4 }));
+    assert_eq!(pq.handle().push(0, 'd'), Err(InsertError::DuplicateKey { key: 0 }));
+
+    assert_eq!(pq.pop_min(), Some((0, 'a')));
+    assert_eq!(pq.insert(0, 'e'), Ok(()));
+    assert_eq!(pq.pop_min(), Some((0, 'e')));
+    assert_eq!(pq.pop_min(), Some((4, 'b')));
+    assert_eq!(pq.pop_min(), None);
+}
+
+#[test]
+fn set_replaces_duplicates() {
+    let drops = Arc::new(AtomicUsize::new(0));
+    let config = Config {
+        duplicates: Duplicates::Replace,
+        ..Config::new(10000)
+    };
+    let pq = MDList::<_>::with_config(config).unwrap();
+    pq.insert(3, (1, DropCounter(drops.clone()))).unwrap();
+    pq.insert(3, (2, DropCounter(drops.clone()))).unwrap();
+    assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+    assert_eq!(pq.pop_min().map(|(key, (i, _))| (key, i)), Some((3, 2)));
+    assert!(pq.pop_min().is_none());
+    assert_eq!(drops.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn inserts_behind_cursor_survive_purge() {
+    let config = Config {
+        purge_threshold: 4,
+        ..Config::new(10000)
+    };
+    let pq = MDList::<_>::with_config(config).unwrap();
+    for key in (0..200).step_by(2) {
+        pq.insert(key, key).unwrap();
+    }
+
+    for key in (0..40).step_by(2) {
+        assert_eq!(pq.pop_min(), Some((key, key)));
+    }
+    // Both land in the prefix that has already been deleted or purged.
+    pq.insert(3, 3).unwrap();
+    pq.insert(38, 38).unwrap();
+
+    assert_eq!(pq.pop_min(), Some((3, 3)));
+    assert_eq!(pq.pop_min(), Some((38, 38)));
+    for key in (40..200).step_by(2) {
+        assert_eq!(pq.pop_min(), Some((key, key)));
+    }
+    assert_eq!(pq.pop_min(), None);
+}
+
+#[test]
+fn custom_dimension_and_basis() {
+    let pq = MDList::<u32, u32, 3, 10>::with_config(Config::new(1000)).unwrap();
+    for key in (0..1000).rev() {
+        pq.insert(key, key).unwrap();
+    }
+    for key in 0..1000 {
+        assert_eq!(pq.pop_min(), Some((key, key)));
+    }
+    assert_eq!(pq.pop_min(), None);
+}
+
+#[test]
+fn unusable_shapes_are_rejected() {
+    assert_eq!(
+        MDList::<u32, u32, 0, 16>::with_config(Config::default()).err(),
+        Some(ConfigError::InvalidDimension { dimension: 0, max: 255 })
+    );
+    assert_eq!(
+        MDList::<u32, u32, 4, 1>::with_config(Config::default()).err(),
+        Some(ConfigError::InvalidBasis { basis: 1 })
+    );
+}
+
+#[test]
+fn keys_outside_range_are_rejected() {
+    let pq = MDList::new(100);
+    assert_eq!(pq.insert(99, ()), Ok(()));
+    assert_eq!(
+        pq.insert(100, ()),
+        Err(InsertError::KeyOutOfRange { key: 100, range: 100 })
+    );
+    assert_eq!(
+        pq.handle().push(u32::MAX, ()),
+        Err(InsertError::KeyOutOfRange { key: u32::MAX as u128, range: 100 })
+    );
+    assert_eq!(pq.pop_min(), Some((99, ())));
+    assert_eq!(pq.pop_min(), None);
+
+    let full = MDList::<_>::with_config(Config::default()).unwrap();
+    assert_eq!(full.insert(u32::MAX, ()), Ok(()));
+}
+
+#[test]
+fn range_must_fit_coordinates() {
+    // Three digits of base 10 hold 1000 keys; the next one widens the basis.
+    let pq = MDList::<u32, u32, 3, 10>::with_config(Config::new(1001)).unwrap();
+    for key in (0..1001).rev().step_by(7) {
+        pq.insert(key, key).unwrap();
+    }
+    let popped: Vec<_> = std::iter::from_fn(|| pq.pop_min()).map(|(key, _)| key).collect();
+    assert_eq!(popped, (0..1001).rev().step_by(7).rev().collect::<Vec<_>>());
+
+    let capacity = 1 << 64;
+    assert_eq!(
+        MDList::<u32, u128, 2>::with_config(Config::new(capacity + 1)).err(),
+        Some(ConfigError::RangeTooLarge { range: capacity + 1, capacity })
+    );
+    assert!(MDList::<u32, u128, 2>::with_config(Config::new(capacity)).is_ok());
+}
+
+#[test]
+fn wide_keys() {
+    let pq = MDList::<&str, u64, 16>::with_config(Config::default()).unwrap();
+    pq.insert(u64::MAX, "max").unwrap();
+    pq.insert(1 << 40, "mid").unwrap();
+    pq.insert(7, "low").unwrap();
+    assert_eq!(pq.pop_min(), Some((7, "low")));
+    assert_eq!(pq.pop_min(), Some((1 << 40, "mid")));
+    assert_eq!(pq.pop_min(), Some((u64::MAX, "max")));
+
+    let pq = MDList::<&str, u128, 32>::with_config(Config::default()).unwrap();
+    pq.insert(u128::MAX, "max").unwrap();
+    pq.insert(u128::from(u64::MAX) << 64, "deadline").unwrap();
+    pq.insert(0, "zero").unwrap();
+    assert_eq!(pq.pop_min(), Some((0, "zero")));
+    assert_eq!(pq.pop_min(), Some((u128::from(u64::MAX) << 64, "deadline")));
+    assert_eq!(pq.pop_min(), Some((u128::MAX, "max")));
+}
+
+#[test]
+fn priority_keys_are_encoded() {
+    let pq = MDList::<&str, i64, 16>::with_config(Config::default()).unwrap();
+    pq.insert(3, "three").unwrap();
+    pq.insert(i64::MIN, "min").unwrap();
+    pq.insert(-3, "minus three").unwrap();
+    assert_eq!(pq.pop_min(), Some((i64::MIN, "min")));
+    assert_eq!(pq.pop_min(), Some((-3, "minus three")));
+    assert_eq!(pq.pop_min(), Some((3, "three")));
+
+    let pq = MDList::<u8, f32>::with_config(Config::default()).unwrap();
+    for (i, key) in [2.5, f32::NEG_INFINITY, -0.5, 0.0].into_iter().enumerate() {
+        pq.insert(key, i as u8).unwrap();
+    }
+    let keys: Vec<_> = std::iter::from_fn(|| pq.pop_min()).map(|(key, _)| key).collect();
+    assert_eq!(keys, [f32::NEG_INFINITY, -0.5, 0.0, 2.5]);
+
+    let pq = MDList::<(), Reverse<u32>>::with_config(Config::default()).unwrap();
+    pq.insert(Reverse(1), ()).unwrap();
+    pq.insert(Reverse(9), ()).unwrap();
+    assert_eq!(pq.pop_min(), Some((Reverse(9), ())));
+
+    // A pair of `u32`s needs 64 bits worth of coordinates.
+    let pq = MDList::<char, (u32, u32), 16>::with_config(Config::default()).unwrap();
+    pq.insert((2, 0), 'c').unwrap();
+    pq.insert((1, 7), 'b').unwrap();
+    pq.insert((1, 3), 'a').unwrap();
+    let popped: String = std::iter::from_fn(|| pq.pop_min()).map(|(_, val)| val).collect();
+    assert_eq!(popped, "abc");
+}
+
+#[test]
+fn key_type_must_fit_coordinates() {
+    assert_eq!(
+        MDList::<u32, u128, 3>::with_config(Config::default()).err(),
+        Some(ConfigError::KeyTooWide {
+            bits: 128,
+            capacity: 1 << 96,
+        })
+    );
+    assert!(MDList::<u32, u128, 4>::with_config(Config::default()).is_ok());
+}
+
+#[test]
+fn default_shape_takes_wide_keys() {
+    let pq = MDList::<&str, u64>::with_config(Config::default()).unwrap();
+    pq.insert(u64::MAX, "max").unwrap();
+    pq.insert(1 << 40, "mid").unwrap();
+    pq.insert(7, "low").unwrap();
+    assert_eq!(pq.successor(7), Some(1 << 40));
+    assert_eq!(pq.pop_min(), Some((7, "low")));
+    assert_eq!(pq.pop_min(), Some((1 << 40, "mid")));
+    assert_eq!(pq.pop_min(), Some((u64::MAX, "max")));
+
+    let pq = MDList::<&str, i128>::with_config(Config::default()).unwrap();
+    pq.insert(i128::MAX, "max").unwrap();
+    pq.insert(-1, "minus one").unwrap();
+    pq.insert(i128::MIN, "min").unwrap();
+    assert_eq!(pq.pop_max(), Some((i128::MAX, "max")));
+    assert_eq!(pq.pop_min(), Some((i128::MIN, "min")));
+    assert_eq!(pq.pop_min(), Some((-1, "minus one")));
+}
+
+#[test]
+fn zero_purge_threshold_is_rejected() {
+    let config = Config {
+        purge_threshold: 0,
+        ..Config::default()
+    };
+    assert_eq!(
+        MDList::<u32>::with_config(config).err(),
+        Some(ConfigError::InvalidPurgeThreshold)
+    );
+}